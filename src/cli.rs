@@ -0,0 +1,768 @@
+use digit_recognition::{
+    dataset, Dataset, Data, Image, Network, HyperParams, TrainingMode, RankedPrediction, Model, ConfigFormat, Search,
+    PopulationBasedTraining, LrFinderHyperParams
+};
+
+use crate::{
+    monitor::{
+        self, monitor, statistics, showcase, evaluation, predictions, model_summary, batch_summary, lr_finder_curve,
+        search_results, population_schedule, Printer
+    },
+    server::{ self, ServerConfig, prediction_json }
+};
+
+use rand::{ SeedableRng, seq::SliceRandom };
+use rand_chacha::ChaCha8Rng;
+use serde_json::json;
+use std::{ fmt, fs, io, thread, path::{ Path, PathBuf }, process::ExitCode, collections::HashMap, num::NonZeroUsize, str::FromStr };
+
+const USAGE: &str = "\
+Usage: digit-recognition <command> [options]
+
+Commands:
+  train       Train a network and save it as a model file
+                --config <file>       TOML or JSON hyperparameters, defaults to the ones in 'main.rs'
+                --data <directory>    Contains 'mnist_train.csv' and 'mnist_test.csv' [default: dataset]
+                --output <file>       [default: model.bin]
+                --resume <file>       Continue the training state of a checkpoint
+  lr-find     Learning rate range test, plots the training loss against the learning rate and suggests an alpha
+                --config <file>       TOML or JSON hyperparameters, defaults to the ones in 'main.rs'
+                --data <directory>    [default: dataset]
+                --min-alpha <alpha>   [default: 1e-6]
+                --max-alpha <alpha>   [default: 1]
+                --steps <count>       Mini-batches, the training data is repeated if it has fewer [default: 300]
+  search      Search hyperparameters, writes the ranked trials as CSV and the best hyperparameters as a config file
+                --config <file>       TOML or JSON search space and strategy [default: search.toml]
+                --base <file>         Hyperparameters the space is sampled around, defaults to the ones in 'main.rs'
+                --data <directory>    [default: dataset]
+                --results <file>      [default: search_results.csv]
+                --output <file>       TOML or JSON config of the best trial [default: best.toml]
+  pbt         Population based training, saves the best member as a model file
+                --config <file>       TOML or JSON population and search space [default: pbt.toml]
+                --base <file>         Hyperparameters of the members with a 'Constant' learning rate scheduler,
+                                      defaults to the ones in 'main.rs'
+                --data <directory>    [default: dataset]
+                --output <file>       [default: model.bin]
+  eval        Accuracy, cost and F1 score of a model on a labeled CSV file
+                --model <file>
+                --data <file>         [default: dataset/mnist_test.csv]
+  predict     Predict the digit of a PGM, PPM or BMP image or of each row of a CSV file
+                --model <file> <input>
+                --top <k>             Most probable digits of each input [default: 1]
+  showcase    Draw random digits of a labeled CSV file next to the predictions of a model
+                --model <file>
+                --data <file>         [default: dataset/mnist_test.csv]
+                -n <count>            [default: 2]
+                --seed <seed>         Draws the same digits for the same seed, random if missing
+  inspect     Composition, activations and size of a model
+                --model <file>
+  batch       Predict the digits of a directory of PGM, PPM or BMP images, preprocessed like MNIST, or of
+              each row of a CSV file and write the digit, its confidence and 3 alternatives as CSV
+                --model <file> <input>
+                --output <file>       [default: predictions.csv]
+  serve       Predict digits over HTTP on localhost, 'GET /health' and 'POST /predict[?top=<k>]'
+              with a JSON body '{\"pixels\": [...]}' of one or more inputs or a PGM image
+                --model <file>
+                --port <port>         [default: 8080]
+                --workers <count>     Threads handling requests, at most 256 [default: number of CPUs]
+                --max-body <bytes>    [default: 1048576]
+                --top <k>             Most probable digits if a request does not ask for a number [default: 1]
+
+Options:
+  --quiet     Only print results
+  --json      Print results as JSON, implies --quiet
+  -h, --help  Print this message
+
+Exit codes: 0 on success, 1 if the command fails, 2 on invalid usage";
+
+const DEFAULT_DATA_DIRECTORY: &str = "dataset";
+const DEFAULT_TEST_DATA: &str = "dataset/mnist_test.csv";
+const DEFAULT_MODEL: &str = "model.bin";
+const DEFAULT_SHOWCASE_COUNT: usize = 2;
+const DEFAULT_TOP: usize = 1;
+const DEFAULT_PORT: usize = 8080;
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+// Each worker is a thread with its own stack
+const MAX_WORKERS: usize = 256;
+const DEFAULT_PREDICTIONS: &str = "predictions.csv";
+const DEFAULT_MIN_ALPHA: f64 = 1e-6;
+const DEFAULT_MAX_ALPHA: f64 = 1.0;
+const DEFAULT_LR_FINDER_STEPS: u32 = 300;
+// Factor of the moving average of the loss in the range test
+const LR_FINDER_SMOOTHING: f64 = 0.98;
+const DEFAULT_SEARCH_CONFIG: &str = "search.toml";
+const DEFAULT_SEARCH_RESULTS: &str = "search_results.csv";
+const DEFAULT_BEST_CONFIG: &str = "best.toml";
+const DEFAULT_PBT_CONFIG: &str = "pbt.toml";
+// Trials printed by 'search', all trials are written to the results file
+const SHOWN_TRIALS: usize = 10;
+// Alternatives to the predicted digit in the output of 'batch'
+const ALTERNATIVES: usize = 3;
+
+// Raw pixel values of each input with the name of its image or CSV row
+type Inputs = Vec<(String, Vec<f64>)>;
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Failure(String)
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{message}\nRun 'digit-recognition --help' for usage"),
+            Self::Failure(message) => write!(f, "{message}")
+        }
+    }
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::Failure(_) => ExitCode::from(1),
+            Self::Usage(_) => ExitCode::from(2)
+        }
+    }
+}
+
+fn failure(error: impl fmt::Display) -> CliError {
+    CliError::Failure(error.to_string())
+}
+
+// PGM, PPM and BMP images in the directory and its subdirectories
+fn image_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        // Symbolic links to directories are not followed, they may link back to a parent
+        if entry.file_type()?.is_dir() {
+            image_files(&path, files)?;
+        } else if matches!(extension.as_deref(), Some("pgm" | "ppm" | "bmp")) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// Fields containing a separator, a quote or a line break are quoted
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Train,
+    LrFind,
+    Search,
+    Pbt,
+    Eval,
+    Predict,
+    Showcase,
+    Inspect,
+    Batch,
+    Serve,
+    Help
+}
+
+impl Command {
+    fn get(name: &str) -> Result<Self, CliError> {
+        match name {
+            "train" => Ok(Self::Train),
+            "lr-find" => Ok(Self::LrFind),
+            "search" => Ok(Self::Search),
+            "pbt" => Ok(Self::Pbt),
+            "eval" => Ok(Self::Eval),
+            "predict" => Ok(Self::Predict),
+            "showcase" => Ok(Self::Showcase),
+            "inspect" => Ok(Self::Inspect),
+            "batch" => Ok(Self::Batch),
+            "serve" => Ok(Self::Serve),
+            "help" | "-h" | "--help" => Ok(Self::Help),
+            _ => Err(CliError::Usage(format!("unknown command '{name}'")))
+        }
+    }
+
+    // Options that take a value and the number of positional arguments
+    fn arguments(&self) -> (&'static [&'static str], usize) {
+        match self {
+            Self::Train => (&["--config", "--data", "--output", "--resume"], 0),
+            Self::LrFind => (&["--config", "--data", "--min-alpha", "--max-alpha", "--steps"], 0),
+            Self::Search => (&["--config", "--base", "--data", "--results", "--output"], 0),
+            Self::Pbt => (&["--config", "--base", "--data", "--output"], 0),
+            Self::Eval => (&["--model", "--data"], 0),
+            Self::Predict => (&["--model", "--top"], 1),
+            Self::Showcase => (&["--model", "--data", "-n", "--seed"], 0),
+            Self::Inspect => (&["--model"], 0),
+            Self::Batch => (&["--model", "--output"], 1),
+            Self::Serve => (&["--model", "--port", "--workers", "--max-body", "--top"], 0),
+            Self::Help => (&[], 0)
+        }
+    }
+}
+
+pub struct Cli {
+    pub command: Command,
+    pub options: HashMap<&'static str, String>,
+    pub positional: Vec<String>,
+    pub quiet: bool,
+    pub json: bool
+}
+
+impl Cli {
+    // Arguments without the program name
+    pub fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut arguments = arguments.into_iter();
+
+        let command = match arguments.next() {
+            Some(name) => Command::get(&name)?,
+            None => return Err(CliError::Usage(String::from("missing command")))
+        };
+
+        let (names, positional_count) = command.arguments();
+
+        let mut cli = Self { command, options: HashMap::new(), positional: Vec::new(), quiet: false, json: false };
+
+        while let Some(argument) = arguments.next() {
+            match argument.as_str() {
+                "--quiet" => cli.quiet = true,
+                "--json" => cli.json = true,
+                "-h" | "--help" => cli.command = Command::Help,
+                option if option.starts_with('-') => {
+                    let name = names.iter()
+                        .find(|&&name| name == option)
+                        .ok_or_else(|| CliError::Usage(format!("unknown option '{option}' for this command")))?;
+
+                    let value = arguments.next()
+                        .ok_or_else(|| CliError::Usage(format!("missing value for '{name}'")))?;
+
+                    if cli.options.insert(name, value).is_some() {
+                        return Err(CliError::Usage(format!("'{name}' is given more than once")));
+                    }
+                },
+                _ => cli.positional.push(argument)
+            }
+        }
+
+        if cli.command != Command::Help && cli.positional.len() != positional_count {
+            return Err(CliError::Usage(match cli.positional.get(positional_count) {
+                Some(argument) => format!("unexpected argument '{argument}'"),
+                None => String::from("missing input file")
+            }));
+        }
+
+        Ok(cli)
+    }
+
+    // 'hyper_params' are used for training if no config file is given
+    pub fn run(&self, hyper_params: fn(&Dataset) -> HyperParams) -> Result<(), CliError> {
+        monitor::set_quiet(self.quiet || self.json);
+
+        match self.command {
+            Command::Train => self.train(hyper_params),
+            Command::LrFind => self.lr_find(hyper_params),
+            Command::Search => self.search(hyper_params),
+            Command::Pbt => self.pbt(hyper_params),
+            Command::Eval => self.eval(),
+            Command::Predict => self.predict(),
+            Command::Showcase => self.showcase(),
+            Command::Inspect => self.inspect(),
+            Command::Batch => self.batch(),
+            Command::Serve => self.serve(),
+            Command::Help => {
+                println!("{USAGE}");
+                Ok(())
+            }
+        }
+    }
+
+    fn option(&self, name: &str, default: &'static str) -> &str {
+        self.options.get(name).map_or(default, String::as_str)
+    }
+
+    fn number<T: FromStr>(&self, name: &str, default: T) -> Result<T, CliError> {
+        match self.options.get(name) {
+            Some(number) => number.parse::<T>()
+                .map_err(|_| CliError::Usage(format!("'{name}' expects a number, got '{number}'"))),
+            None => Ok(default)
+        }
+    }
+
+    // Hyperparameters of the config file given with 'option', the ones of 'main.rs' otherwise
+    fn load_hyper_params(
+        &self,
+        option: &str,
+        data: &Dataset,
+        hyper_params: fn(&Dataset) -> HyperParams
+    ) -> Result<HyperParams, CliError> {
+        let Some(path) = self.options.get(option) else {
+            return Ok(hyper_params(data));
+        };
+
+        let hyper_params = monitor(|| HyperParams::load(path), "Loading config")
+            .map_err(|error| CliError::Failure(format!("{path}: {error}")))?;
+
+        hyper_params.validate_dimensions(data.test.inputs[0].len(), data.test.targets[0].len())
+            .map_err(|error| CliError::Failure(format!("{path}: {error}")))?;
+
+        Ok(hyper_params)
+    }
+
+    fn load_model(&self) -> Result<Model, CliError> {
+        let path = self.option("--model", DEFAULT_MODEL);
+
+        monitor(|| Model::load(path), "Loading network parameters")
+            .map_err(|error| CliError::Failure(format!("{path}: {error}")))
+    }
+
+    // Labeled samples with the normalization of the model
+    fn load_data(&self, network: &Network) -> Result<Data, CliError> {
+        let path = self.option("--data", DEFAULT_TEST_DATA);
+        let data = monitor(|| Data::read_csv(path, network.hyper_params.normalization), "Parsing CSV").map_err(failure)?;
+
+        let composition = &network.hyper_params.composition;
+
+        if data.inputs[0].len() != composition[0] || data.targets[0].len() != composition[composition.len() - 1] {
+            return Err(CliError::Failure(format!(
+                "{path}: samples have {} pixel values, the model expects {}",
+                data.inputs[0].len(),
+                composition[0]
+            )));
+        }
+
+        Ok(data)
+    }
+
+    fn train(&self, hyper_params: fn(&Dataset) -> HyperParams) -> Result<(), CliError> {
+        let directory = self.option("--data", DEFAULT_DATA_DIRECTORY);
+        let data = monitor(|| Dataset::new(directory), "Parsing CSV").map_err(failure)?;
+
+        let hyper_params = self.load_hyper_params("--config", &data, hyper_params)?;
+
+        let mut network = match self.options.get("--resume") {
+            // Continue an interrupted training run from its last checkpoint
+            Some(path) => monitor(|| Network::resume(hyper_params, path), "Resuming training")
+                .map_err(|error| CliError::Failure(format!("{path}: {error}")))?,
+            None => monitor(|| Network::new(hyper_params), "Initializing network").map_err(failure)?
+        };
+
+        monitor(|| network.train(&data.train, &data.validation, &mut Printer), "Training network");
+
+        let output = self.option("--output", DEFAULT_MODEL);
+
+        monitor(|| network.save(output), "Saving network parameters")
+            .map_err(|error| CliError::Failure(format!("{output}: {error}")))?;
+
+        match self.json {
+            true => {
+                let (accuracy, cost) = network.test(&data.test);
+
+                println!("{}", json!({
+                    "model": output,
+                    "epochs": network.epoch,
+                    "seed": network.seed,
+                    "accuracy": accuracy,
+                    "cost": cost
+                }));
+            },
+            false => statistics(&mut network, &data.test)
+        }
+
+        Ok(())
+    }
+
+    // Trains a fresh network with growing learning rates, nothing is saved
+    fn lr_find(&self, hyper_params: fn(&Dataset) -> HyperParams) -> Result<(), CliError> {
+        let lr_finder_hyper_params = LrFinderHyperParams {
+            min_alpha: self.number("--min-alpha", DEFAULT_MIN_ALPHA)?,
+            max_alpha: self.number("--max-alpha", DEFAULT_MAX_ALPHA)?,
+            steps: self.number("--steps", DEFAULT_LR_FINDER_STEPS)?,
+            smoothing: LR_FINDER_SMOOTHING
+        };
+
+        lr_finder_hyper_params.validate().map_err(|error| CliError::Usage(error.to_string()))?;
+
+        let directory = self.option("--data", DEFAULT_DATA_DIRECTORY);
+        let data = monitor(|| Dataset::new(directory), "Parsing CSV").map_err(failure)?;
+
+        let hyper_params = self.load_hyper_params("--config", &data, hyper_params)?;
+
+        if !matches!(hyper_params.training_mode, TrainingMode::MiniBatch) {
+            return Err(CliError::Failure(String::from("the learning rate range test requires mini-batch training")));
+        }
+
+        let mut network = monitor(|| Network::new(hyper_params), "Initializing network").map_err(failure)?;
+        let lr_finder = monitor(
+            || network.find_learning_rate(&data.train, lr_finder_hyper_params),
+            "Learning rate range test"
+        );
+
+        match self.json {
+            true => println!("{}", json!({
+                "alphas": lr_finder.alphas,
+                "losses": lr_finder.losses,
+                "suggestion": lr_finder.suggestion
+            })),
+            false => lr_finder_curve(&lr_finder)
+        }
+
+        Ok(())
+    }
+
+    // Fields of the search space that are left out keep the values of the base hyperparameters
+    fn search(&self, hyper_params: fn(&Dataset) -> HyperParams) -> Result<(), CliError> {
+        let path = self.option("--config", DEFAULT_SEARCH_CONFIG);
+        let search = monitor(|| Search::load(path), "Loading search config")
+            .map_err(|error| CliError::Failure(format!("{path}: {error}")))?;
+
+        // Checked before searching, the search may take hours
+        let output = self.option("--output", DEFAULT_BEST_CONFIG);
+        ConfigFormat::get(output).map_err(|error| CliError::Usage(error.to_string()))?;
+
+        let directory = self.option("--data", DEFAULT_DATA_DIRECTORY);
+        let data = monitor(|| Dataset::new(directory), "Parsing CSV").map_err(failure)?;
+
+        let base = self.load_hyper_params("--base", &data, hyper_params)?;
+        search.validate_base(&base).map_err(failure)?;
+
+        let results = monitor(|| search.run(&base, &data.train, &data.validation, &mut Printer), "Searching hyperparameters")
+            .map_err(failure)?;
+
+        let results_path = self.option("--results", DEFAULT_SEARCH_RESULTS);
+
+        monitor(|| results.save(results_path), "Saving search results")
+            .map_err(|error| CliError::Failure(format!("{results_path}: {error}")))?;
+
+        monitor(|| results.best().save(output), "Saving best hyperparameters")
+            .map_err(|error| CliError::Failure(format!("{output}: {error}")))?;
+
+        match self.json {
+            true => {
+                let best = &results.trials[0];
+
+                println!("{}", json!({
+                    "results": results_path,
+                    "config": output,
+                    "trials": results.trials.len(),
+                    "best": {
+                        "trial": best.id,
+                        "score": best.score,
+                        "accuracy": best.accuracy,
+                        "cost": best.cost,
+                        "epochs": best.epochs
+                    }
+                }));
+            },
+            false => search_results(&results, SHOWN_TRIALS)
+        }
+
+        Ok(())
+    }
+
+    // Trains a population of networks in parallel that copies and perturbs its best members
+    fn pbt(&self, hyper_params: fn(&Dataset) -> HyperParams) -> Result<(), CliError> {
+        let path = self.option("--config", DEFAULT_PBT_CONFIG);
+        let pbt = monitor(|| PopulationBasedTraining::load(path), "Loading population config")
+            .map_err(|error| CliError::Failure(format!("{path}: {error}")))?;
+
+        let directory = self.option("--data", DEFAULT_DATA_DIRECTORY);
+        let data = monitor(|| Dataset::new(directory), "Parsing CSV").map_err(failure)?;
+
+        let base = self.load_hyper_params("--base", &data, hyper_params)?;
+        pbt.validate_base(&base).map_err(failure)?;
+
+        let member = monitor(|| pbt.run(&base, &data.train, &data.validation, &mut Printer), "Population based training")
+            .map_err(failure)?;
+
+        let output = self.option("--output", DEFAULT_MODEL);
+
+        monitor(|| member.network.save(output), "Saving network parameters")
+            .map_err(|error| CliError::Failure(format!("{output}: {error}")))?;
+
+        match self.json {
+            true => {
+                let mut network = member.network;
+                let (accuracy, cost) = network.test(&data.test);
+
+                println!("{}", json!({
+                    "model": output,
+                    "member": member.id,
+                    "score": member.score,
+                    "schedule": member.schedule.iter()
+                        .map(|&(epoch, alpha)| json!({ "epoch": epoch, "alpha": alpha }))
+                        .collect::<Vec<_>>(),
+                    "accuracy": accuracy,
+                    "cost": cost
+                }));
+            },
+            false => {
+                population_schedule(&member);
+
+                let mut network = member.network;
+                statistics(&mut network, &data.test);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn eval(&self) -> Result<(), CliError> {
+        let mut network = Network::from(self.load_model()?);
+        let data = self.load_data(&network)?;
+
+        let (accuracy, cost) = network.test(&data);
+        let f1_score = network.compute_f1_score(&data);
+
+        match self.json {
+            true => println!("{}", json!({
+                "accuracy": accuracy,
+                "cost": cost,
+                "f1_score": f1_score,
+                "samples": data.inputs.len()
+            })),
+            false => evaluation(accuracy, cost, f1_score, data.inputs.len())
+        }
+
+        Ok(())
+    }
+
+    // Raw pixel values of the input file, one list for each digit
+    fn read_inputs(&self, model: &Model) -> Result<Inputs, CliError> {
+        let path = &self.positional[0];
+        let inputs = model.composition()[0];
+
+        let extension = Path::new(path).extension().and_then(|extension| extension.to_str());
+
+        match extension {
+            Some("csv") => {
+                let rows = dataset::read_rows(path).map_err(failure)?;
+
+                // Rows of a labeled CSV file start with the label
+                let skip = match rows[0].len() {
+                    length if length == inputs => 0,
+                    length if length == inputs + 1 => 1,
+                    length => return Err(CliError::Failure(format!(
+                        "{path}: rows have {length} values, the model expects {inputs} pixel values"
+                    )))
+                };
+
+                Ok(rows.into_iter()
+                    .enumerate()
+                    .map(|(index, row)| (format!("Row {}", index + 1), row[skip..].to_vec()))
+                    .collect())
+            },
+            Some("pgm" | "ppm" | "bmp") => {
+                let image = Image::read(path).map_err(|error| CliError::Failure(format!("{path}: {error}")))?;
+
+                if image.pixels.len() != inputs {
+                    return Err(CliError::Failure(format!(
+                        "{path}: image has {}x{} pixels, the model expects {inputs} pixels",
+                        image.width,
+                        image.height
+                    )));
+                }
+
+                Ok(vec![(path.clone(), image.pixels)])
+            },
+            _ => Err(CliError::Usage(format!("unsupported input '{path}', expected a .pgm, .ppm, .bmp or .csv file")))
+        }
+    }
+
+    fn predict(&self) -> Result<(), CliError> {
+        let top = self.number("--top", DEFAULT_TOP)?;
+        let model = self.load_model()?;
+
+        let (sources, inputs): (Vec<String>, Vec<Vec<f64>>) = self.read_inputs(&model)?.into_iter().unzip();
+
+        let ranked = model.predict_batch(&inputs, top)
+            .map_err(|error| CliError::Failure(format!("{}: {error}", self.positional[0])))?;
+
+        // Each prediction with the name of its image or CSV row
+        let predictions: Vec<(String, RankedPrediction)> = sources.into_iter().zip(ranked).collect();
+
+        match self.json {
+            true => {
+                let predictions = predictions.iter()
+                    .map(|(source, prediction)| {
+                        let mut prediction = prediction_json(prediction);
+                        prediction["source"] = json!(source);
+
+                        prediction
+                    })
+                    .collect::<Vec<_>>();
+
+                println!("{}", json!(predictions));
+            },
+            false => self::predictions(&predictions)
+        }
+
+        Ok(())
+    }
+
+    fn showcase(&self) -> Result<(), CliError> {
+        let count = self.number("-n", DEFAULT_SHOWCASE_COUNT)?;
+        let seed = self.number("--seed", rand::random())?;
+
+        let mut network = Network::from(self.load_model()?);
+        let data = self.load_data(&network)?;
+
+        // Drawn the same way with and without '--json', so both show the same digits for a seed
+        let data_indices = (0..data.inputs.len()).collect::<Vec<usize>>();
+        let samples = data_indices.choose_multiple(&mut ChaCha8Rng::seed_from_u64(seed), count)
+            .copied()
+            .collect::<Vec<usize>>();
+
+        match self.json {
+            true => {
+                let samples = samples.iter()
+                    .map(|&index| {
+                        network.forward(&data.inputs[index]);
+                        let outputs = network.outputs.last().unwrap();
+
+                        json!({
+                            "index": index,
+                            "target": Network::arg_max(&data.targets[index]),
+                            "digit": Network::arg_max(outputs),
+                            "outputs": outputs
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                println!("{}", json!(samples));
+            },
+            false => showcase(&mut network, &data, &samples)
+        }
+
+        Ok(())
+    }
+
+    fn inspect(&self) -> Result<(), CliError> {
+        let model = self.load_model()?;
+        let path = self.option("--model", DEFAULT_MODEL);
+        let size = fs::metadata(path).map_err(failure)?.len();
+
+        match self.json {
+            true => {
+                println!("{}", json!({
+                    "path": path,
+                    "size": size,
+                    "version": Model::VERSION,
+                    "parameters": model.parameter_count(),
+                    "composition": model.composition(),
+                    "activations": model.activations(),
+                    "normalization": model.normalization()
+                }));
+            },
+            false => model_summary(&model, path, size)
+        }
+
+        Ok(())
+    }
+
+    // Preprocessed pixels of each image in the directory with its path relative to the directory,
+    // images that can not be decoded are reported and skipped
+    fn read_images(&self, directory: &str) -> Result<(Inputs, usize), CliError> {
+        let mut files = Vec::new();
+        image_files(Path::new(directory), &mut files).map_err(|error| CliError::Failure(format!("{directory}: {error}")))?;
+        files.sort();
+
+        let mut images = Vec::with_capacity(files.len());
+        let mut skipped = 0;
+
+        for file in files {
+            let name = file.strip_prefix(directory).unwrap_or(&file).to_string_lossy().into_owned();
+
+            match Image::read(&file.to_string_lossy()) {
+                Ok(image) => images.push((name, image.to_mnist().pixels)),
+                Err(error) => {
+                    eprintln!("ERROR: {name}: {error}");
+                    skipped += 1;
+                }
+            }
+        }
+
+        Ok((images, skipped))
+    }
+
+    fn batch(&self) -> Result<(), CliError> {
+        let input = &self.positional[0];
+        let output = self.option("--output", DEFAULT_PREDICTIONS);
+
+        let model = self.load_model()?;
+
+        let (inputs, skipped) = match Path::new(input).is_dir() {
+            true => monitor(|| self.read_images(input), "Reading images")?,
+            false => (self.read_inputs(&model)?, 0)
+        };
+
+        if inputs.is_empty() {
+            return Err(CliError::Failure(format!("{input}: no PGM, PPM or BMP images found")));
+        }
+
+        let (names, inputs): (Vec<String>, Vec<Vec<f64>>) = inputs.into_iter().unzip();
+
+        let predictions = monitor(|| model.predict_batch(&inputs, ALTERNATIVES + 1), "Predicting digits")
+            .map_err(|error| CliError::Failure(format!("{}: {error}", names[error.input()])))?;
+
+        let alternatives = (1..=ALTERNATIVES)
+            .map(|index| format!("alternative_{index},confidence_{index}"))
+            .collect::<Vec<String>>();
+
+        let mut csv = format!("file,digit,confidence,{}\n", alternatives.join(","));
+
+        for (name, prediction) in names.iter().zip(&predictions) {
+            // Models with fewer classes than alternatives leave the missing fields empty, so every row fits the header
+            let fields = (0..=ALTERNATIVES)
+                .map(|index| match prediction.top.get(index) {
+                    Some((digit, probability)) => format!("{digit},{probability:.6}"),
+                    None => String::from(",")
+                })
+                .collect::<Vec<String>>();
+
+            csv.push_str(&format!("{},{}\n", csv_field(name), fields.join(",")));
+        }
+
+        fs::write(output, csv).map_err(|error| CliError::Failure(format!("{output}: {error}")))?;
+
+        match self.json {
+            true => println!("{}", json!({ "predicted": predictions.len(), "skipped": skipped, "output": output })),
+            false => batch_summary(predictions.len(), skipped, output)
+        }
+
+        Ok(())
+    }
+
+    fn serve(&self) -> Result<(), CliError> {
+        let port = self.number("--port", DEFAULT_PORT)?;
+        let port = u16::try_from(port).map_err(|_| CliError::Usage(format!("'--port' expects a port number, got {port}")))?;
+
+        let workers = match self.options.get("--workers") {
+            Some(_) => self.number("--workers", 0)?,
+            None => thread::available_parallelism().map_or(1, NonZeroUsize::get).min(MAX_WORKERS)
+        };
+
+        if !(1..=MAX_WORKERS).contains(&workers) {
+            return Err(CliError::Usage(format!("'--workers' expects 1 to {MAX_WORKERS} threads, got {workers}")));
+        }
+
+        let config = ServerConfig {
+            port,
+            workers,
+            max_body_size: self.number("--max-body", DEFAULT_MAX_BODY_SIZE)?,
+            top: self.number("--top", DEFAULT_TOP)?
+        };
+
+        let model = self.load_model()?;
+
+        server::serve(model, &config).map_err(|error| CliError::Failure(format!("port {port}: {error}")))
+    }
+}
@@ -1,60 +1,129 @@
+//! Reading the MNIST CSV files and scaling the inputs.
+
 use super::network::state::Vec2D;
 
-use std::{ fs::File, io::{ BufReader, BufRead } };
+use serde::{ Serialize, Deserialize };
+use std::{ fs::File, io::{ self, BufReader, BufRead } };
+
+// Classes of the MNIST labels
+const DIGITS: usize = 10;
+
+/// Min-max scaling of the raw input values onto [0, 1], stored with saved models.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Normalization {
+    /// Raw value scaled onto 0
+    pub min: f64,
+    /// Raw value scaled onto 1
+    pub max: f64
+}
 
-// 'inputs' consists of a list of handwritten digits
-// A digit is represented by 784 grayscale values ranging from 0 (black) - 255 (white)
+impl Normalization {
+    /// Grayscale values of the MNIST images.
+    pub const GRAYSCALE: Self = Self { min: 0.0, max: 255.0 };
+
+    /// Scales a raw value, values outside of 'min' - 'max' end up outside of [0, 1].
+    pub fn apply(&self, value: f64) -> f64 {
+        (value - self.min) / (self.max - self.min)
+    }
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self::GRAYSCALE
+    }
+}
+
+/// 'inputs' consists of a list of handwritten digits and 'targets' of their one-hot encoded labels.
+/// A digit is represented by 784 normalized grayscale values ranging from 0 (black) - 1 (white).
 pub struct Data {
+    /// Normalized pixel values of each sample
     pub inputs: Vec2D,
+    /// One-hot encoded label of each sample
     pub targets: Vec2D
 }
 
 impl Data { 
-    pub fn one_hot_encode(num: usize) -> Vec<f64> {
-        let mut vec = vec![0.0; 10];
+    pub(crate) fn one_hot_encode(num: usize, classes: usize) -> Vec<f64> {
+        let mut vec = vec![0.0; classes];
         vec[num] = 1.0;
         
         vec
     }
 
-    fn parse_csv(name: &str) -> Self {
-        let file_name = format!("dataset/mnist_{name}.csv");
-        let file = File::open(file_name).expect("ERROR: opening file");
-        let reader = BufReader::new(file);
-
-        let lines: Vec2D = reader.lines()
-            .map(|line| {
-                line.expect("ERROR: reading line")
-                    .split(',')
-                    .map(|num| num.parse::<f64>().expect("ERROR: parsing f64"))
-                    .collect::<Vec<f64>>()
-            })
-            .collect();
+    /// Rows of a MNIST CSV file, each one the label followed by the raw pixel values.
+    pub fn read_csv(path: &str, normalization: Normalization) -> io::Result<Self> {
+        let lines = read_rows(path)?;
+
+        for (index, line) in lines.iter().enumerate() {
+            if !(0.0..DIGITS as f64).contains(&line[0]) || line[0].fract() != 0.0 {
+                return Err(invalid_data(path, index, "label has to be a digit from 0 to 9"));
+            }
+        }
 
         let inputs: Vec2D = lines
             .iter()
-            .map(|line| line[1..].iter().map(|&value| value / 255.0).collect()) // Normalize grayscale values
+            .map(|line| line[1..].iter().map(|&value| normalization.apply(value)).collect()) // Normalize grayscale values
             .collect();
         
         // One-hot encode targets to allow comparison between target output and actual output.
-        let targets: Vec2D = lines.iter().map(|line| Self::one_hot_encode(line[0] as usize)).collect();
+        let targets: Vec2D = lines.iter().map(|line| Self::one_hot_encode(line[0] as usize, DIGITS)).collect();
 
-        Self {
+        Ok(Self {
             inputs,
             targets
+        })
+    }
+}
+
+fn invalid_data(path: &str, index: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{path}, line {}: {message}", index + 1))
+}
+
+/// Comma separated values of each line, all lines need the same number of values.
+pub fn read_rows(path: &str) -> io::Result<Vec2D> {
+    let file = File::open(path).map_err(|error| io::Error::new(error.kind(), format!("{path}: {error}")))?;
+    let reader = BufReader::new(file);
+
+    let mut rows: Vec2D = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let row = line?
+            .split(',')
+            .map(|num| num.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|error| invalid_data(path, index, &error.to_string()))?;
+
+        if rows.first().is_some_and(|first| first.len() != row.len()) {
+            return Err(invalid_data(path, index, "wrong number of values"));
         }
+
+        rows.push(row);
+    }
+
+    match rows.is_empty() {
+        true => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{path}: no samples"))),
+        false => Ok(rows)
     }
 }
 
+/// MNIST training, validation and test data.
 pub struct Dataset {
+    /// First 80% of the training samples
     pub train: Data,
+    /// Last 20% of the training samples
     pub validation: Data,
-    pub test: Data 
+    /// Samples of 'mnist_test.csv'
+    pub test: Data,
+    /// Scaling of the raw pixel values of all samples
+    pub normalization: Normalization
 }
 
 impl Dataset {
-    pub fn new() -> Self {
-        let training_data = Data::parse_csv("train");
+    /// Reads 'mnist_train.csv' and 'mnist_test.csv' from 'directory', the last 20% of the training
+    /// samples are used for validation.
+    pub fn new(directory: &str) -> io::Result<Self> {
+        let training_data = Data::read_csv(&format!("{directory}/mnist_train.csv"), Normalization::GRAYSCALE)?;
 
         // MNIST Dataset does not provide a validation set, which is why we split the training set 
         let total_lines = training_data.targets.len();
@@ -63,10 +132,11 @@ impl Dataset {
         let (train_inputs, validation_inputs) = training_data.inputs.split_at(validation_start_index);
         let (train_targets, validation_targets) = training_data.targets.split_at(validation_start_index);
 
-        Self {
+        Ok(Self {
             train: Data { inputs: train_inputs.to_owned(), targets: train_targets.to_owned() },
             validation: Data { inputs: validation_inputs.to_owned(), targets: validation_targets.to_owned() },
-            test: Data::parse_csv("test")
-        }
+            test: Data::read_csv(&format!("{directory}/mnist_test.csv"), Normalization::GRAYSCALE)?,
+            normalization: Normalization::GRAYSCALE
+        })
     }
 }
@@ -0,0 +1,444 @@
+//! Decoding PGM, PPM and BMP images into MNIST-style inputs.
+
+use std::{ fmt, fs, io };
+
+/// Errors of reading and decoding images.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The image file could not be read
+    Io(io::Error),
+    /// Neither a PGM, PPM nor an uncompressed BMP image
+    UnsupportedFormat,
+    /// Missing or invalid dimensions or maximum value
+    InvalidHeader,
+    /// Fewer pixels than announced by the header
+    UnexpectedEnd,
+    /// Pixel above the maximum value or outside of the palette
+    InvalidPixel,
+    /// Wider or higher than 16384 pixels
+    TooLarge
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "image file: {error}"),
+            Self::UnsupportedFormat => write!(f, "unsupported image format, expected a PGM, PPM or uncompressed BMP image"),
+            Self::InvalidHeader => write!(f, "invalid image header"),
+            Self::UnexpectedEnd => write!(f, "image file ends unexpectedly"),
+            Self::InvalidPixel => write!(f, "pixel value exceeds the maximum value of the image"),
+            Self::TooLarge => write!(f, "image exceeds {MAX_SIZE} pixels in width or height")
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+// Side length of the MNIST images and of the box the digits are scaled into
+const MNIST_SIZE: usize = 28;
+const MNIST_DIGIT_SIZE: usize = 20;
+// Larger images are rejected before anything is allocated, scans of a single digit are far smaller
+const MAX_SIZE: usize = 16384;
+
+/// Grayscale image, pixels row by row scaled onto 0 (black) - 255 (white) like the MNIST dataset.
+#[derive(Clone, Debug)]
+pub struct Image {
+    /// Pixels in each row
+    pub width: usize,
+    /// Number of rows
+    pub height: usize,
+    /// 'width' times 'height' grayscale values
+    pub pixels: Vec<f64>
+}
+
+// Whitespace separated tokens of a PGM header, comments start with '#' and end with the line
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<&'a [u8]> {
+        loop {
+            let byte = *self.bytes.get(self.position)?;
+
+            if byte == b'#' {
+                while self.bytes.get(self.position).is_some_and(|&byte| byte != b'\n') {
+                    self.position += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let start = self.position;
+
+        while self.bytes.get(self.position).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+
+        Some(&self.bytes[start..self.position])
+    }
+
+    fn next_number(&mut self) -> Result<usize, ImageError> {
+        let token = self.next().ok_or(ImageError::UnexpectedEnd)?;
+
+        std::str::from_utf8(token).ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or(ImageError::InvalidHeader)
+    }
+}
+
+// Perceived brightness of a color
+fn luminance(red: f64, green: f64, blue: f64) -> f64 {
+    0.299 * red + 0.587 * green + 0.114 * blue
+}
+
+fn read_u16(bytes: &[u8], position: usize) -> Result<u16, ImageError> {
+    bytes.get(position..position + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or(ImageError::UnexpectedEnd)
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> Result<u32, ImageError> {
+    bytes.get(position..position + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(ImageError::UnexpectedEnd)
+}
+
+impl Image {
+    /// Reads a PGM (P5, P2), PPM (P6, P3) or uncompressed BMP image, colors are converted to grayscale.
+    pub fn read(path: &str) -> Result<Self, ImageError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    /// Decodes the bytes of an image file like [`Image::read`], e.g. the body of a request.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImageError> {
+        match bytes.get(..2) {
+            Some(b"P5") => Self::decode_netpbm(bytes, true, 1),
+            Some(b"P2") => Self::decode_netpbm(bytes, false, 1),
+            Some(b"P6") => Self::decode_netpbm(bytes, true, 3),
+            Some(b"P3") => Self::decode_netpbm(bytes, false, 3),
+            Some(b"BM") => Self::decode_bmp(bytes),
+            _ => Err(ImageError::UnsupportedFormat)
+        }
+    }
+
+    // PGM and PPM images with one or three samples per pixel
+    fn decode_netpbm(bytes: &[u8], binary: bool, channels: usize) -> Result<Self, ImageError> {
+        let mut tokens = Tokens { bytes, position: 2 };
+
+        let width = tokens.next_number()?;
+        let height = tokens.next_number()?;
+        let max_value = tokens.next_number()?;
+
+        if width == 0 || height == 0 || !(1..=u16::MAX as usize).contains(&max_value) {
+            return Err(ImageError::InvalidHeader);
+        }
+
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(ImageError::TooLarge);
+        }
+
+        let count = width.checked_mul(height)
+            .and_then(|count| count.checked_mul(channels))
+            .ok_or(ImageError::TooLarge)?;
+
+        let values: Vec<usize> = match binary {
+            // A single whitespace separates the header from the pixels, which take two bytes above 255
+            true => {
+                let start = tokens.position + 1;
+                let size = if max_value > 255 { 2 } else { 1 };
+
+                let end = count.checked_mul(size)
+                    .and_then(|length| start.checked_add(length))
+                    .ok_or(ImageError::TooLarge)?;
+
+                let pixels = bytes.get(start..end).ok_or(ImageError::UnexpectedEnd)?;
+
+                pixels.chunks(size)
+                    .map(|chunk| chunk.iter().fold(0, |value, &byte| (value << 8) | byte as usize))
+                    .collect()
+            },
+            false => (0..count).map(|_| tokens.next_number()).collect::<Result<_, _>>()?
+        };
+
+        if values.iter().any(|&value| value > max_value) {
+            return Err(ImageError::InvalidPixel);
+        }
+
+        let values = values.iter().map(|&value| value as f64 / max_value as f64 * 255.0).collect::<Vec<f64>>();
+
+        let pixels = match channels {
+            1 => values,
+            _ => values.chunks(channels).map(|color| luminance(color[0], color[1], color[2])).collect()
+        };
+
+        Ok(Self { width, height, pixels })
+    }
+
+    // Uncompressed bitmaps with 1, 4 or 8 bits per pixel and a palette, or 24 and 32 bits per pixel
+    fn decode_bmp(bytes: &[u8]) -> Result<Self, ImageError> {
+        let offset = read_u32(bytes, 10)? as usize;
+        let header_size = read_u32(bytes, 14)? as usize;
+
+        // Rows are stored from bottom to top unless the height is negative
+        let width = read_u32(bytes, 18)? as i32;
+        let height = read_u32(bytes, 22)? as i32;
+        let bits = read_u16(bytes, 28)? as usize;
+        let compression = read_u32(bytes, 30)?;
+
+        if header_size < 40 || width <= 0 || height == 0 {
+            return Err(ImageError::InvalidHeader);
+        }
+
+        if compression != 0 || ![1, 4, 8, 24, 32].contains(&bits) {
+            return Err(ImageError::UnsupportedFormat);
+        }
+
+        let (width, bottom_up) = (width as usize, height > 0);
+        let height = height.unsigned_abs() as usize;
+
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(ImageError::TooLarge);
+        }
+
+        // Palette entries are stored as blue, green, red and a reserved byte
+        let palette = match bits {
+            1 | 4 | 8 => {
+                let colors = match read_u32(bytes, 46)? {
+                    0 => 1 << bits,
+                    colors => colors as usize
+                };
+
+                let start = 14 + header_size;
+                let end = colors.checked_mul(4)
+                    .and_then(|length| start.checked_add(length))
+                    .ok_or(ImageError::UnexpectedEnd)?;
+
+                let entries = bytes.get(start..end).ok_or(ImageError::UnexpectedEnd)?;
+
+                entries.chunks(4)
+                    .map(|color| luminance(color[2] as f64, color[1] as f64, color[0] as f64))
+                    .collect()
+            },
+            _ => Vec::new()
+        };
+
+        // Rows are padded to a multiple of four bytes, all of them have to be present before the pixels are allocated
+        let row_size = (bits * width).div_ceil(32) * 4;
+
+        let end = row_size.checked_mul(height)
+            .and_then(|length| offset.checked_add(length))
+            .ok_or(ImageError::TooLarge)?;
+
+        if end > bytes.len() {
+            return Err(ImageError::UnexpectedEnd);
+        }
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let row = if bottom_up { height - 1 - y } else { y };
+            let start = offset + row * row_size;
+            let row = &bytes[start..start + row_size];
+
+            for x in 0..width {
+                let value = match bits {
+                    24 | 32 => {
+                        let color = &row[x * bits / 8..];
+                        luminance(color[2] as f64, color[1] as f64, color[0] as f64)
+                    },
+                    _ => {
+                        let bit = x * bits;
+                        let index = (row[bit / 8] >> (8 - bits - bit % 8)) as usize & ((1 << bits) - 1);
+
+                        *palette.get(index).ok_or(ImageError::InvalidPixel)?
+                    }
+                };
+
+                pixels.push(value);
+            }
+        }
+
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Preprocesses a scanned digit like the MNIST images: dark digits on a light background are inverted,
+    /// the digit is cropped, scaled into a 20x20 box and centered by its center of mass in a 28x28 image.
+    pub fn to_mnist(&self) -> Self {
+        let mut image = self.clone();
+
+        // The border mostly shows the background
+        let border = (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| x == 0 || y == 0 || x == image.width - 1 || y == image.height - 1)
+            .map(|(x, y)| image.pixels[y * image.width + x])
+            .collect::<Vec<f64>>();
+
+        if border.iter().sum::<f64>() / border.len() as f64 > 127.5 {
+            image.pixels.iter_mut().for_each(|pixel| *pixel = 255.0 - *pixel);
+        }
+
+        let Some(digit) = image.crop() else {
+            return Self { width: MNIST_SIZE, height: MNIST_SIZE, pixels: vec![0.0; MNIST_SIZE * MNIST_SIZE] };
+        };
+
+        // The longer side fills the box, the aspect ratio is kept
+        let scale = MNIST_DIGIT_SIZE as f64 / digit.width.max(digit.height) as f64;
+        let width = ((digit.width as f64 * scale).round() as usize).clamp(1, MNIST_DIGIT_SIZE);
+        let height = ((digit.height as f64 * scale).round() as usize).clamp(1, MNIST_DIGIT_SIZE);
+
+        digit.resize(width, height).center(MNIST_SIZE)
+    }
+
+    // Bounding box of the pixels brighter than the background, 'None' if the image is blank
+    fn crop(&self) -> Option<Self> {
+        let threshold = self.pixels.iter().copied().fold(0.0, f64::max) * 0.2;
+
+        if threshold == 0.0 {
+            return None;
+        }
+
+        let (mut left, mut top, mut right, mut bottom) = (self.width, self.height, 0, 0);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.pixels[y * self.width + x] > threshold {
+                    (left, top) = (left.min(x), top.min(y));
+                    (right, bottom) = (right.max(x), bottom.max(y));
+                }
+            }
+        }
+
+        let (width, height) = (right - left + 1, bottom - top + 1);
+
+        Some(Self {
+            width,
+            height,
+            pixels: (top..=bottom)
+                .flat_map(|y| (left..=right).map(move |x| (x, y)))
+                .map(|(x, y)| self.pixels[y * self.width + x])
+                .collect()
+        })
+    }
+
+    // Each pixel averages the pixels it covers, so downscaling large scans does not skip thin strokes
+    fn resize(&self, width: usize, height: usize) -> Self {
+        let range = |index: usize, size: usize, source: usize| {
+            let start = index * source / size;
+            let end = ((index + 1) * source).div_ceil(size).max(start + 1);
+
+            start..end
+        };
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (columns, rows) = (range(x, width, self.width), range(y, height, self.height));
+                let count = columns.len() * rows.len();
+
+                rows.flat_map(|row| columns.clone().map(move |column| (column, row)))
+                    .map(|(column, row)| self.pixels[row * self.width + column])
+                    .sum::<f64>() / count as f64
+            })
+            .collect();
+
+        Self { width, height, pixels }
+    }
+
+    // Places the image in a black square so that its center of mass is in the middle
+    fn center(&self, size: usize) -> Self {
+        let mass = self.pixels.iter().sum::<f64>();
+
+        let (center_x, center_y) = match mass > 0.0 {
+            true => self.pixels.iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(x, y), (index, &pixel)| {
+                    (x + (index % self.width) as f64 * pixel / mass, y + (index / self.width) as f64 * pixel / mass)
+                }),
+            false => ((self.width - 1) as f64 / 2.0, (self.height - 1) as f64 / 2.0)
+        };
+
+        // Offsets of the image in the square, the image stays inside the square
+        let offset = |center: f64, length: usize| {
+            ((size as f64 / 2.0 - 0.5 - center).round() as isize).clamp(0, (size - length) as isize) as usize
+        };
+
+        let (offset_x, offset_y) = (offset(center_x, self.width), offset(center_y, self.height));
+        let mut pixels = vec![0.0; size * size];
+
+        for (index, &pixel) in self.pixels.iter().enumerate() {
+            pixels[(index / self.width + offset_y) * size + index % self.width + offset_x] = pixel;
+        }
+
+        Self { width: size, height: size, pixels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Header of an uncompressed 24-bit bitmap, the pixels start right after it
+    fn bmp_header(width: i32, height: i32) -> Vec<u8> {
+        let mut bytes = vec![0; 54];
+
+        bytes[..2].copy_from_slice(b"BM");
+        bytes[10..14].copy_from_slice(&54u32.to_le_bytes());
+        bytes[14..18].copy_from_slice(&40u32.to_le_bytes());
+        bytes[18..22].copy_from_slice(&width.to_le_bytes());
+        bytes[22..26].copy_from_slice(&height.to_le_bytes());
+        bytes[26..28].copy_from_slice(&1u16.to_le_bytes());
+        bytes[28..30].copy_from_slice(&24u16.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn decodes_pgm() {
+        let image = Image::decode(b"P5 2 1 255\n\x00\xff").unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, [0.0, 255.0]);
+    }
+
+    #[test]
+    fn decodes_bmp() {
+        // One white pixel, the row is padded to four bytes
+        let mut bytes = bmp_header(1, 1);
+        bytes.extend([255, 255, 255, 0]);
+
+        let image = Image::decode(&bytes).unwrap();
+
+        assert_eq!((image.width, image.height), (1, 1));
+        assert!((image.pixels[0] - 255.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_truncated_pixels() {
+        assert!(matches!(Image::decode(b"P5 2 2 255\n\x00\x00\x00"), Err(ImageError::UnexpectedEnd)));
+        assert!(matches!(Image::decode(b"P2 2 2 255\n0 0 0"), Err(ImageError::UnexpectedEnd)));
+        assert!(matches!(Image::decode(&bmp_header(2, 2)), Err(ImageError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        assert!(matches!(Image::decode(b"P5 2"), Err(ImageError::UnexpectedEnd)));
+        assert!(matches!(Image::decode(&bmp_header(1, 1)[..20]), Err(ImageError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn rejects_oversized_header() {
+        assert!(matches!(Image::decode(&bmp_header(0x7fffffff, 0x100000)), Err(ImageError::TooLarge)));
+        assert!(matches!(Image::decode(&bmp_header(0x7fffffff, 0x7fffffff)), Err(ImageError::TooLarge)));
+        assert!(matches!(Image::decode(b"P5 4294967296 4294967296 255\n"), Err(ImageError::TooLarge)));
+        assert!(matches!(Image::decode(b"P6 16384 16384 65535\n"), Err(ImageError::UnexpectedEnd)));
+    }
+}
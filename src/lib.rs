@@ -0,0 +1,76 @@
+//! Handwritten digit recognition with a neural network written from scratch.
+//!
+//! Train a network on the MNIST dataset and save it as a model file:
+//!
+//! ```no_run
+//! use digit_recognition::{ Dataset, HyperParams, Network, Silent };
+//!
+//! let data = Dataset::new("dataset").expect("dataset");
+//! let hyper_params = HyperParams::load("config.toml").expect("config");
+//!
+//! let mut network = Network::new(hyper_params).expect("hyperparameters");
+//! network.train(&data.train, &data.validation, &mut Silent);
+//! network.save("model.bin").expect("model");
+//! ```
+//!
+//! Load the model to predict digits from raw grayscale values, a [`Model`] only predicts and can be
+//! shared across threads:
+//!
+//! ```no_run
+//! use digit_recognition::{ Model, Prediction };
+//!
+//! let model = Model::load("model.bin").expect("model");
+//! let prediction = Prediction::new(model.predict(&[0.0; 784]));
+//!
+//! println!("{} ({:.2})", prediction.digit, prediction.probabilities[prediction.digit]);
+//! ```
+//!
+//! Every training feature is configured by [`HyperParams`], see `config.toml` for an example. The library
+//! never prints, the progress of training is reported to an [`Observer`].
+
+#![warn(missing_docs)]
+
+pub mod dataset;
+pub mod image;
+mod network;
+
+pub use dataset::{ Dataset, Data, Normalization };
+pub use image::{ Image, ImageError };
+pub use network::{
+    state::{ Network, HyperParams, TrainingMode },
+    model::{ Model, ModelError },
+    checkpoint::CheckpointError,
+    config::{ ConfigError, ConfigFormat },
+    prediction::{ Prediction, RankedPrediction, PredictionError },
+    search::{ Search, SearchSpace, SearchStrategy, Range, SearchResults, Trial },
+    population::{ PopulationBasedTraining, Member },
+    observer::{ Observer, Silent, EpochReport, LbfgsReport },
+    optimizations::{ lr_finder::{ LrFinderHyperParams, LrFinder }, lbfgs::LbfgsStop }
+};
+
+/// Types of the fields of [`HyperParams`], each one configures a training feature.
+pub mod hyper_params {
+    pub use crate::network::{
+        optimizations::{
+            activations::{ Activation, ActivationType },
+            regularization::{ Regularization, ElasticNetRegularization, ElasticNetRegularizer, Dropout, MaxNormConstraint },
+            learning_rate::{
+                LearningRate, SchedulerType, Restart, Decay, DecayMethod, Interval, Warmup, CosineAnnealingHyperParams,
+                OneCycleHyperParams, TriangularHyperParams, ReduceOnPlateauHyperParams
+            },
+            optimizer::OptimizerType,
+            sgd::MomentumHyperParams,
+            rmsprop::RmsPropHyperParams,
+            adagrad::AdaGradHyperParams,
+            adam::{ AdamHyperParams, AdamWHyperParams },
+            lamb::LambHyperParams,
+            lars::LarsHyperParams,
+            lookahead::LookaheadHyperParams,
+            early_stopping::{ EarlyStopping, Criterion, BestModel, Metric, Direction },
+            gradient_clipping::GradientClipping,
+            weight_averaging::{ WeightAveraging, Ema, SwaSchedule, Averaging },
+            lbfgs::LbfgsHyperParams
+        },
+        checkpoint::Checkpoint
+    };
+}
@@ -1,25 +1,38 @@
-mod dataset;
-mod network;
+mod cli;
 mod monitor;
+mod server;
 
-use dataset::Dataset;
-use network::{ 
-    optimizations::{ 
-        activations::{ Activation, ActivationType::* },
-        regularization::{ Regularization, ElasticNetRegularization, ElasticNetRegularizer, Dropout },
-        learning_rate::{ LearningRate, Restart, Decay, DecayMethod },
-        adam::AdamHyperParams,
-        early_stopping::EarlyStopping
-    }, 
-    state::{ Network, HyperParams } 
+use digit_recognition::{
+    hyper_params::{
+        Activation, ActivationType::*,
+        Regularization, ElasticNetRegularization, ElasticNetRegularizer, Dropout, MaxNormConstraint,
+        LearningRate, SchedulerType, Restart, Decay, DecayMethod, Interval,
+        OptimizerType,
+        AdamHyperParams,
+        EarlyStopping, Criterion,
+        GradientClipping,
+        WeightAveraging
+    },
+    Dataset, HyperParams, TrainingMode
 };
-use monitor::{ monitor, statistics, showcase };
+use cli::Cli;
 
-fn main() {
-    let data = monitor(Dataset::new, "Parsing CSV");
+use std::{ env, process::ExitCode };
 
+fn main() -> ExitCode {
+    match Cli::parse(env::args().skip(1)).and_then(|cli| cli.run(hyper_params)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("ERROR: {error}");
+            error.exit_code()
+        }
+    }
+}
+
+// Used by 'train' if no config file is given, 'hyper_params.save("config.toml")' writes them as a starting point
+fn hyper_params(data: &Dataset) -> HyperParams {
     // Manually tune Hyperparameters
-    let hyper_params = HyperParams {
+    HyperParams {
         // Number of neurons in each layer
         composition: vec![data.test.inputs[0].len(), 16, 16, data.test.targets[0].len()],
         // Activation functions for hidden and output layers
@@ -34,45 +47,77 @@ fn main() {
                 input_layer: 2e-3,
                 hidden_layer: 5e-3
             },
-            max_norm_constraint: 8.0
+            // Incoming weights of a neuron are rescaled onto this L2 norm after each step
+            max_norm_constraint: MaxNormConstraint {
+                limit: 8.0,
+                // Optional limit for each hidden and output layer
+                layer_limits: None
+            }
         },
         learning_rate: LearningRate {
             alpha: 0.01,
-            // Set the learning rate back to a specific value after a specific interval
-            restart: Some(Restart {
-                interval: 10, // Epochs
-                alpha: 1e-3
-            }),
-            decay: Some(Decay {
-                method: DecayMethod::Exponential,
-                rate: 0.9,
-                // Interval in epochs after which the learning rate is decayed
-                step: 2 
-            }),
+            // Constant, Decay, CosineAnnealing, OneCycle, Triangular or ReduceOnPlateau
+            scheduler: SchedulerType::Decay {
+                // Set the learning rate back to a specific value after a specific interval
+                restart: Some(Restart {
+                    interval: 10, // Epochs
+                    alpha: 1e-3
+                }),
+                decay: Some(Decay {
+                    method: DecayMethod::Exponential,
+                    rate: 0.9,
+                    // Interval in epochs after which the learning rate is decayed
+                    step: 2 
+                })
+            },
+            // Update the learning rate after every step or once per epoch
+            interval: Interval::Epoch,
+            // Linear warmup over the first steps, e.g. 'Some(Warmup { steps: 500 })'
+            warmup: None
         },
-        optimizer: AdamHyperParams {
+        // Sgd, Momentum, Nesterov, RmsProp, AdaGrad, Adam, AdamW, Lamb or Lars
+        optimizer: OptimizerType::Adam(AdamHyperParams {
             beta_1: 0.9,
             beta_2: 0.999,
             epsilon: 1e-8,
+            amsgrad: false
+        }),
+        // Wrap the optimizer, e.g. 'Some(LookaheadHyperParams { steps: 5, alpha: 0.5 })'
+        lookahead: None,
+        // Clip gradients by value and / or by their global L2 norm, 'None' disables clipping
+        gradient_clipping: GradientClipping {
+            value: None,
+            norm: None
         },
         batch_size: 4,
-        // If the mean difference of the network accuracy over the last 
-        // <patience> epochs exceeds <stability_threshold>, stop training
         early_stopping: EarlyStopping {
-            stability_threshold: 5e-3,
-            patience: 15
-        }
-    };
-
-    let mut network = monitor(|| Network::new(hyper_params), "Initializing network");
-    
-    monitor(|| network.train(&data.train, &data.validation), "Training network");
-    monitor(|| network.save(), "Saving network parameters");
-
-    // Initalize network with provided Hyperparameters and parameters from the save file
-    // let mut network = monitor(|| Network::load(hyper_params), "Loading network parameters");
-
-    statistics(&mut network, &data.test);
-    // Visualize handwritten digits and display network predictions
-    showcase(&mut network, &data.test, 2);
+            // If the mean difference of the network accuracy over the last 
+            // <patience> epochs exceeds <stability_threshold>, stop training
+            criterion: Criterion::Stability {
+                stability_threshold: 5e-3,
+                patience: 15
+            },
+            // Or patience on the best epoch, e.g. 'Criterion::Best(BestModel { metric: Metric::Loss, 
+            // direction: Direction::Minimize, patience: 10, min_delta: 1e-4, restore_best: true })'
+            // Stop after a fixed number of epochs at the latest, e.g. 'Some(100)'
+            max_epochs: None
+        },
+        // Shadow copies of the parameters, evaluated and saved instead of the last parameters if applied
+        weight_averaging: WeightAveraging {
+            // e.g. 'Some(Ema { decay: 0.999 })'
+            ema: None,
+            // Average over the last epochs or learning rate restart cycles, e.g. 'Some(SwaSchedule::Cycles(3))'
+            swa: None,
+            // 'Some(Averaging::Ema)' or 'Some(Averaging::Swa)'
+            apply: None
+        },
+        // MiniBatch or full-batch Lbfgs, which requires an infinite max norm limit and no gradient clipping
+        training_mode: TrainingMode::MiniBatch,
+        // Save the complete training state, e.g. 'Some(Checkpoint { interval: 5, path: String::from("checkpoint.txt") })'
+        checkpoint: None,
+        // Scaling of the raw pixel values, saved with the model
+        normalization: data.normalization,
+        // Seed for reproducible runs, e.g. 'Some(42)', drawn randomly if 'None' and shown in the statistics
+        seed: None
+    }
 }
@@ -1,11 +1,22 @@
-#![allow(dead_code)]
-
-use crate::{ dataset::Data, network::optimizations::learning_rate::LearningRate };
-use super::network::state::{ Network, HyperParams };
+use digit_recognition::{
+    hyper_params::{ LearningRate, SchedulerType, OptimizerType, Criterion, AdamHyperParams },
+    Data, Network, HyperParams, TrainingMode, Trial, SearchResults, Member, Model, Prediction, RankedPrediction,
+    LrFinder, LbfgsStop, Observer, EpochReport, LbfgsReport, CheckpointError
+};
 
 use std::time::{ Instant, Duration };
-use rand::{ thread_rng, seq::SliceRandom };
-use std::cmp::Ordering;
+use std::sync::atomic::{ self, AtomicBool };
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Suppress the progress output of 'monitor' and 'Printer', results and errors are still printed
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, atomic::Ordering::Relaxed);
+}
+
+fn is_quiet() -> bool {
+    QUIET.load(atomic::Ordering::Relaxed)
+}
 
 fn print_header(message: &str) {
     println!("\n{:―^50}\n", format!(" {} ", message));    
@@ -32,6 +43,10 @@ pub fn monitor<T, F>(function: F, message: &str) -> T
 where
     F: FnOnce() -> T,
 {
+    if is_quiet() {
+        return function();
+    }
+
     print_header(message);
     
     let timestamp = Instant::now();
@@ -43,24 +58,36 @@ where
     return_value
 }
 
-pub fn monitor_training(
-    epoch: u32, learning_rate: f64, accuracy: f64, cost: f64, duration: Duration, early_stop: bool
-) {
+fn monitor_training(report: &EpochReport) {
+    let EpochReport { epoch, learning_rate, accuracy, cost, regularization_penalty, clipped, duration, stopped } = *report;
+
+    if is_quiet() {
+        return;
+    }
+
     if epoch == 1 {
         print_centered(String::from("Epochs:\n"));        
     }
+
+    // Number of steps with clipped gradients, only shown if gradient clipping is enabled
+    let clipped = match clipped {
+        Some(clipped) => format!(", Clipped: {}", clipped),
+        None => String::new()
+    };
     
     print_centered(
         format!(
-            "[{:0>2?}] LR: {:.2e}, Acc.: {:0>2.2}%, Cost: {:.3}", 
+            "[{:0>2?}] LR: {:.2e}, Acc.: {:0>2.2}%, Cost: {:.3} (Reg.: {:.3}){}", 
             epoch,
             learning_rate,
             accuracy * 100.0,
-            cost
+            cost,
+            regularization_penalty,
+            clipped
         )
     );
 
-    if early_stop {
+    if stopped {
         println!();
         print_centered(String::from("Early Stopping"));
         println!();
@@ -68,16 +95,153 @@ pub fn monitor_training(
     }
 }
 
+// Progress of full-batch L-BFGS training, 'stop' is set after the last iteration
+fn monitor_lbfgs(report: &LbfgsReport) {
+    let LbfgsReport { iteration, step_size, accuracy, cost, regularization_penalty, duration, stop } = *report;
+
+    if is_quiet() {
+        return;
+    }
+
+    if iteration == 1 {
+        print_centered(String::from("Iterations:\n"));        
+    }
+
+    print_centered(
+        format!(
+            "[{:0>2?}] Step: {:.2e}, Acc.: {:0>2.2}%, Cost: {:.3} (Reg.: {:.3})", 
+            iteration,
+            step_size,
+            accuracy * 100.0,
+            cost,
+            regularization_penalty
+        )
+    );
+
+    if let Some(stop) = stop {
+        let reason = match stop {
+            LbfgsStop::Converged => "Converged",
+            LbfgsStop::LineSearchFailed => "Line Search Failed",
+            LbfgsStop::MaxIterations => "Maximum Iterations"
+        };
+
+        println!();
+        print_centered(String::from(reason));
+        println!();
+        print_centered(format!("Avg. Duration: {:.2?}\n", duration / iteration));
+    }
+}
+
+fn print_adam(hyper_params: &AdamHyperParams, alpha: &f64) {
+    print_table(
+        format!("Alpha: {:.2e}", alpha),
+        format!("Epsilon: {:e}", hyper_params.epsilon)
+    );
+    print_table(
+        format!("Beta 1: {}", hyper_params.beta_1),
+        format!("Beta 2: {}", hyper_params.beta_2)
+    );
+    println!("{:<50}", format!("AMSGrad: {}", hyper_params.amsgrad));
+}
+
+fn print_optimizer(optimizer: &OptimizerType, alpha: &f64) {
+    match optimizer {
+        OptimizerType::Sgd => {
+            print_subheader("SGD Optimizer");
+            println!("{:<50}", format!("Alpha: {:.2e}", alpha));
+        },
+        OptimizerType::Momentum(hyper_params) => {
+            print_subheader("Momentum Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Beta: {}", hyper_params.beta)
+            );
+        },
+        OptimizerType::Nesterov(hyper_params) => {
+            print_subheader("Nesterov Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Beta: {}", hyper_params.beta)
+            );
+        },
+        OptimizerType::RmsProp(hyper_params) => {
+            print_subheader("RMSProp Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Epsilon: {:e}", hyper_params.epsilon)
+            );
+            println!("{:<50}", format!("Beta: {}", hyper_params.beta));
+        },
+        OptimizerType::AdaGrad(hyper_params) => {
+            print_subheader("AdaGrad Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Epsilon: {:e}", hyper_params.epsilon)
+            );
+        },
+        OptimizerType::Adam(hyper_params) => {
+            print_subheader("Adam Optimizer");
+            print_adam(hyper_params, alpha);
+        },
+        OptimizerType::AdamW(hyper_params) => {
+            print_subheader("AdamW Optimizer");
+            print_adam(&hyper_params.adam, alpha);
+            println!("{:<50}", format!("Weight Decay: {:e}", hyper_params.weight_decay));
+        },
+        OptimizerType::Lamb(hyper_params) => {
+            print_subheader("LAMB Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Epsilon: {:e}", hyper_params.epsilon)
+            );
+            print_table(
+                format!("Beta 1: {}", hyper_params.beta_1),
+                format!("Beta 2: {}", hyper_params.beta_2)
+            );
+            println!("{:<50}", format!("Weight Decay: {:e}", hyper_params.weight_decay));
+        },
+        OptimizerType::Lars(hyper_params) => {
+            print_subheader("LARS Optimizer");
+
+            print_table(
+                format!("Alpha: {:.2e}", alpha),
+                format!("Momentum: {}", hyper_params.momentum)
+            );
+            print_table(
+                format!("Weight Decay: {:e}", hyper_params.weight_decay),
+                format!("Trust Coefficient: {:e}", hyper_params.trust_coefficient)
+            );
+        }
+    }
+    
+    println!();
+}
+
 pub fn statistics(network: &mut Network, data: &Data) {
     let (accuracy, avg_cost) = network.test(data);
 
     print_header("Neural Network Statistics");
 
     let HyperParams { 
-        composition, regularization, learning_rate, optimizer, batch_size, early_stopping, ..
+        composition, 
+        regularization, 
+        learning_rate, 
+        optimizer, 
+        lookahead, 
+        gradient_clipping, 
+        batch_size, 
+        early_stopping, 
+        weight_averaging, 
+        training_mode, 
+        ..
     } = &network.hyper_params;
     
-    let LearningRate { alpha, decay, restart } = learning_rate;
+    let LearningRate { alpha, scheduler, interval, warmup } = learning_rate;
 
     print_subheader("Composition");
 
@@ -103,27 +267,55 @@ pub fn statistics(network: &mut Network, data: &Data) {
         format!("L2 Weights: {:e}", regularization.elastic_net.weights.l2),
         format!("L2 Biases: {:e}", regularization.elastic_net.biases.l2)
     );
-    println!("{:<50}\n", format!("Max Norm Constraint: {}", regularization.max_norm_constraint));
+    match &regularization.max_norm_constraint.layer_limits {
+        Some(layer_limits) => println!("{:<50}\n", format!("Max Norm Constraint: {:?}", layer_limits)),
+        None => println!("{:<50}\n", format!("Max Norm Constraint: {}", regularization.max_norm_constraint.limit))
+    }
     println!();
 
-    print_subheader("Adam Optimizer");
+    print_optimizer(optimizer, alpha);
 
-    print_table(
-        format!("Alpha: {:.2e}", alpha),
-        format!("Epsilon: {:e}", optimizer.epsilon)
-    );
-    print_table(
-        format!("Beta 1: {}", optimizer.beta_1),
-        format!("Beta 2: {}", optimizer.beta_2)
-    );
-    println!();
+    if let Some(lookahead) = lookahead {
+        print_subheader("Lookahead");
+
+        print_table(
+            format!("Steps: {}", lookahead.steps),
+            format!("Alpha: {}", lookahead.alpha)
+        );
+        println!();
+    }
+
+    if gradient_clipping.is_enabled() {
+        print_subheader("Gradient Clipping");
+
+        print_table(
+            format!("Value: {}", gradient_clipping.value.map_or(String::from("-"), |value| format!("{:e}", value))),
+            format!("Global Norm: {}", gradient_clipping.norm.map_or(String::from("-"), |norm| format!("{:e}", norm)))
+        );
+        println!();
+    }
 
     print_subheader("Training");
 
     print_table(
         format!("Batch Size: {}", batch_size),
-        format!("Iterations: {}", network.optimizer.iteration)
+        format!("Iterations: {}", network.iteration())
     );
+    print_table(
+        format!("Epochs: {}", network.epoch),
+        format!("Seed: {}", network.seed)
+    );
+
+    if let TrainingMode::Lbfgs(lbfgs) = training_mode {
+        print_table(
+            String::from("Mode: L-BFGS"),
+            format!("History Size: {}", lbfgs.history_size)
+        );
+        print_table(
+            format!("Max. Iterations: {}", lbfgs.max_iterations),
+            format!("Tolerance: {:e}", lbfgs.tolerance)
+        );
+    }
     println!();
 
     print_subheader("Dropout Rate");
@@ -136,40 +328,290 @@ pub fn statistics(network: &mut Network, data: &Data) {
     
     print_subheader("Learning Rate");
 
-    if let Some(decay) = &decay {
-        print_table(
-            format!("Decay Method: {:#?}", decay.method),
-            format!("Decay Rate: {:e}", decay.rate)
-        );        
-    }
+    print_table(
+        format!("Interval: {:?}", interval),
+        format!("Warmup Steps: {}", warmup.map_or(String::from("-"), |warmup| warmup.steps.to_string()))
+    );
 
-    if let Some(restart) = &restart {
-        print_table(
-            format!("Restart Interval: {}", restart.interval),
-            format!("Restart Value: {:e}", restart.alpha)
-        );
+    match scheduler {
+        SchedulerType::Constant => println!("{:<50}", "Scheduler: Constant"),
+        SchedulerType::Decay { decay, restart } => {
+            if let Some(decay) = &decay {
+                print_table(
+                    format!("Decay Method: {:#?}", decay.method),
+                    format!("Decay Rate: {:e}", decay.rate)
+                );        
+            }
+
+            if let Some(restart) = &restart {
+                print_table(
+                    format!("Restart Interval: {}", restart.interval),
+                    format!("Restart Value: {:e}", restart.alpha)
+                );
+            }
+        },
+        SchedulerType::CosineAnnealing(hyper_params) => {
+            println!("{:<50}", "Scheduler: Cosine Annealing");
+            print_table(
+                format!("Min. Alpha: {:e}", hyper_params.min_alpha),
+                format!("Period: {} (x{})", hyper_params.period, hyper_params.period_multiplier)
+            );
+        },
+        SchedulerType::OneCycle(hyper_params) => {
+            println!("{:<50}", "Scheduler: One-Cycle");
+            print_table(
+                format!("Max. Alpha: {:e}", hyper_params.max_alpha),
+                format!("Epochs: {}", hyper_params.epochs)
+            );
+            print_table(
+                format!("Warmup Fraction: {}", hyper_params.warmup_fraction),
+                format!("Final Divisor: {}", hyper_params.final_divisor)
+            );
+        },
+        SchedulerType::Triangular(hyper_params) => {
+            println!("{:<50}", "Scheduler: Triangular");
+            print_table(
+                format!("Max. Alpha: {:e}", hyper_params.max_alpha),
+                format!("Half Period: {}", hyper_params.half_period)
+            );
+        },
+        SchedulerType::ReduceOnPlateau(hyper_params) => {
+            println!("{:<50}", "Scheduler: Reduce on Plateau");
+            print_table(
+                format!("Factor: {}", hyper_params.factor),
+                format!("Patience: {}", hyper_params.patience)
+            );
+            print_table(
+                format!("Min. Delta: {:e}", hyper_params.min_delta),
+                format!("Min. Alpha: {:e}", hyper_params.min_alpha)
+            );
+        }
     }
     println!();
     
     print_subheader("Early Stopping");
+    match &early_stopping.criterion {
+        Criterion::Stability { stability_threshold, patience } => {
+            print_table(
+                format!("Patience: {}", patience),
+                format!("Stability Threshold: {:e}", stability_threshold)
+            );
+        },
+        Criterion::Best(best_model) => {
+            print_table(
+                format!("Metric: {:?}", best_model.metric),
+                format!("Direction: {:?}", best_model.direction)
+            );
+            print_table(
+                format!("Patience: {}", best_model.patience),
+                format!("Min. Delta: {:e}", best_model.min_delta)
+            );
+            println!("{:<50}", format!("Restore Best: {}", best_model.restore_best));
+        }
+    }
+
     print_table(
-        format!("Patience: {}", early_stopping.patience),
-        format!("Stability Threshold: {:e}", early_stopping.stability_threshold)
+        format!("Max. Epochs: {}", early_stopping.max_epochs.map_or(String::from("-"), |max_epochs| max_epochs.to_string())),
+        format!("Best Epoch: {}", network.best_epoch().map_or(String::from("-"), |(epoch, score)| format!("{} ({:.3})", epoch, score)))
     );
     println!();
     
+    if weight_averaging.ema.is_some() || weight_averaging.swa.is_some() {
+        print_subheader("Weight Averaging");
+
+        print_table(
+            format!("EMA Decay: {}", weight_averaging.ema.map_or(String::from("-"), |ema| ema.decay.to_string())),
+            format!("SWA: {}", weight_averaging.swa.map_or(String::from("-"), |swa| format!("{:?}", swa)))
+        );
+        println!("{:<50}\n", format!("Applied: {}", weight_averaging.apply.map_or(String::from("-"), |apply| format!("{:?}", apply))));
+    }
+
     print_subheader("Evaluation");
 
     print_table(
         format!("Accuracy: {:.2}%", accuracy * 100.0),
         format!("Cost: {:.3?}", avg_cost)
     );
+    println!("{:<50}", format!("Regularization Penalty: {:.3}", network.compute_regularization_penalty()));
     println!();
     
     println!();
     print_end();
 }
 
+fn monitor_trial(trial: &Trial) {
+    if is_quiet() {
+        return;
+    }
+
+    print_centered(
+        format!(
+            "[Trial {:0>2}] Epochs: {}, Acc.: {:0>2.2}%, Score: {:.4}",
+            trial.id,
+            trial.epochs,
+            trial.accuracy * 100.0,
+            trial.score
+        )
+    );
+}
+
+// Members are sorted from best to worst, the last 'replaced' ones were just replaced by perturbed copies
+fn monitor_population(epoch: u32, members: &[Member], replaced: usize) {
+    if is_quiet() || members.len() == replaced {
+        return;
+    }
+
+    let kept = &members[..members.len() - replaced];
+    let best = &kept[0];
+
+    print_centered(
+        format!(
+            "[{:0>2?}] Best: #{} (Acc.: {:0>2.2}%, Score: {:.4}), Replaced: {}",
+            epoch,
+            best.id,
+            best.accuracy * 100.0,
+            best.score,
+            replaced
+        )
+    );
+}
+
+// Prints the progress of training, searches and populations unless the output is quiet
+pub struct Printer;
+
+impl Observer for Printer {
+    fn epoch(&mut self, report: &EpochReport) {
+        monitor_training(report);
+    }
+
+    fn lbfgs_iteration(&mut self, report: &LbfgsReport) {
+        monitor_lbfgs(report);
+    }
+
+    fn trial(&mut self, trial: &Trial) {
+        monitor_trial(trial);
+    }
+
+    fn population(&mut self, epoch: u32, members: &[Member], replaced: usize) {
+        monitor_population(epoch, members, replaced);
+    }
+
+    // Training continues, so the failed write is reported even if the output is quiet
+    fn checkpoint_failed(&mut self, path: &str, error: &CheckpointError) {
+        eprintln!("ERROR: {path}: {error}");
+    }
+}
+
+// Learning rate of the best member's lineage at the start of each interval
+pub fn population_schedule(member: &Member) {
+    print_header("Population Based Training");
+
+    print_subheader(&format!("Schedule of Member #{}", member.id));
+
+    for (epoch, alpha) in &member.schedule {
+        print_table(format!("Epoch: {}", epoch), format!("Alpha: {:.2e}", alpha));
+    }
+    println!();
+
+    print_table(
+        format!("Accuracy: {:.2}%", member.accuracy * 100.0),
+        format!("Score: {:.4}", member.score)
+    );
+    println!();
+
+    print_end();
+}
+
+// Ranked table of all trials, wider than the other sections to fit the sampled hyperparameters
+pub fn search_results(results: &SearchResults, count: usize) {
+    print_header("Hyperparameter Search");
+
+    println!(
+        "{:>4} {:>5} {:>8} {:>7} {:>6} {:>14} {:>9} {:>9} {:>9} {:>9} {:>9} {:>5}",
+        "Rank", "Trial", "Score", "Acc.", "Epochs", "Hidden", "Alpha", "Drop In", "Drop Hid.", "L1", "L2", "Batch"
+    );
+
+    for (rank, trial) in results.trials.iter().take(count).enumerate() {
+        let HyperParams { composition, regularization, learning_rate, batch_size, .. } = &trial.hyper_params;
+
+        println!(
+            "{:>4} {:>5} {:>8.4} {:>6.2}% {:>6} {:>14} {:>9.2e} {:>9.2e} {:>9.2e} {:>9.2e} {:>9.2e} {:>5}",
+            rank + 1,
+            trial.id,
+            trial.score,
+            trial.accuracy * 100.0,
+            trial.epochs,
+            format!("{:?}", &composition[1..composition.len() - 1]),
+            learning_rate.alpha,
+            regularization.dropout_rate.input_layer,
+            regularization.dropout_rate.hidden_layer,
+            regularization.elastic_net.weights.l1,
+            regularization.elastic_net.weights.l2,
+            batch_size
+        );
+    }
+    println!();
+
+    print_end();
+}
+
+// ASCII plot of the smoothed loss against the logarithm of the learning rate
+pub fn lr_finder_curve(lr_finder: &LrFinder) {
+    const WIDTH: usize = 40;
+    const HEIGHT: usize = 15;
+
+    print_header("Learning Rate Range Test");
+
+    let LrFinder { alphas, losses, suggestion } = lr_finder;
+
+    if alphas.len() < 2 {
+        print_centered(String::from("Not enough steps to plot"));
+        print_end();
+        return;
+    }
+
+    let (min_log, max_log) = (alphas[0].log10(), alphas.last().unwrap().log10());
+    let min_loss = losses.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_loss = losses.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    let column = |alpha: f64| (((alpha.log10() - min_log) / (max_log - min_log)) * (WIDTH - 1) as f64).round() as usize;
+    let row = |loss: f64| (((max_loss - loss) / (max_loss - min_loss).max(f64::EPSILON)) * (HEIGHT - 1) as f64).round() as usize;
+
+    let mut grid = vec![vec![' '; WIDTH]; HEIGHT];
+
+    if let Some(suggestion) = suggestion {
+        for line in grid.iter_mut() {
+            line[column(*suggestion)] = '|';
+        }
+    }
+
+    for (&alpha, &loss) in alphas.iter().zip(losses.iter()) {
+        grid[row(loss)][column(alpha)] = '*';
+    }
+
+    for (index, line) in grid.iter().enumerate() {
+        let label = match index {
+            0 => format!("{:.3}", max_loss),
+            _ if index == HEIGHT - 1 => format!("{:.3}", min_loss),
+            _ => String::new()
+        };
+
+        println!("{:>8} |{}", label, line.iter().collect::<String>());
+    }
+
+    println!("{:>8} +{}", "", "-".repeat(WIDTH));
+    println!("{:>10}{:>w$}", format!("{:.0e}", alphas[0]), format!("{:.0e}", alphas.last().unwrap()), w = WIDTH);
+    println!();
+
+    match suggestion {
+        Some(suggestion) => print_centered(format!("Suggested Alpha: {:.2e}", suggestion)),
+        None => print_centered(String::from("No suggestion"))
+    }
+    println!();
+
+    print_end();
+}
+
 fn visualize_data(data: &Data, data_index: usize) {
     const SHADES: &str = " .:-=+*#%";
     
@@ -202,34 +644,109 @@ fn print_predictions(network: &mut Network, data: &Data, data_index: usize) {
 
     print_subheader("Predictions");
 
-    let mut predictions = network.outputs.last().unwrap().iter()
-        .enumerate()
-        .map(|(number, &output)| (number, output * 100.0))
-        .collect::<Vec<(usize, f64)>>();
-    
-    predictions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    let prediction = Prediction::from_outputs(network.outputs.last().unwrap());
 
-    for (number, output) in predictions {            
-        match (0.0..10.0).contains(&output) {
-            true => print_centered(format!("{}:  {:.2}%", number, output)), // Two spaces 
-            false => print_centered(format!("{}: {:.2}%", number, output)) // One space
-        } 
+    for (number, probability) in prediction.top(prediction.probabilities.len()) {
+        // Padded to align percentages below 10%
+        print_centered(format!("{}: {:>5.2}%", number, probability * 100.0));
     }
 
     println!();
 }
 
-pub fn showcase(network: &mut Network, data: &Data, num_tests: usize) {
+pub fn showcase(network: &mut Network, data: &Data, data_indices: &[usize]) {
     print_header("Showcase");
 
-    let mut rng = thread_rng();
-    let data_indices = (0..data.inputs.len()).collect::<Vec<usize>>();
-    let random_data_indices = data_indices.choose_multiple(&mut rng, num_tests);
-
-    for &data_index in random_data_indices {
+    for &data_index in data_indices {
         visualize_data(data, data_index);
         print_predictions(network, data, data_index);
     }
     
     print_end();
 }
+
+// Results of 'eval' on a labeled dataset
+pub fn evaluation(accuracy: f64, cost: f64, f1_score: f64, samples: usize) {
+    print_header("Evaluation");
+
+    print_table(
+        format!("Accuracy: {:.2}%", accuracy * 100.0),
+        format!("Cost: {:.3}", cost)
+    );
+    print_table(
+        format!("F1 Score: {:.4}", f1_score),
+        format!("Samples: {}", samples)
+    );
+    println!();
+
+    print_end();
+}
+
+// Each prediction with the name of its input
+pub fn predictions(predictions: &[(String, RankedPrediction)]) {
+    print_header("Predictions");
+
+    for (source, prediction) in predictions {
+        let top = prediction.top.iter()
+            .map(|(digit, probability)| format!("{}: {:.2}%", digit, probability * 100.0))
+            .collect::<Vec<String>>();
+
+        print_table(source.clone(), top.join(", "));
+    }
+    println!();
+
+    print_end();
+}
+
+// Results of 'batch'
+pub fn batch_summary(predicted: usize, skipped: usize, output: &str) {
+    print_header("Batch Prediction");
+
+    print_table(format!("Predicted: {}", predicted), format!("Skipped: {}", skipped));
+    println!("{:<50}\n", format!("Output: {}", output));
+
+    print_end();
+}
+
+// Address and number of worker threads of 'serve', followed by a line for each request
+pub fn serving(address: &str, workers: usize) {
+    if is_quiet() {
+        return;
+    }
+
+    print_header("Serving");
+
+    print_table(format!("Address: {}", address), format!("Workers: {}", workers));
+    println!();
+}
+
+pub fn request(method: &str, path: &str, status: u16, duration: Duration) {
+    if is_quiet() {
+        return;
+    }
+
+    println!("{} {} {} ({:.2?})", method, path, status, duration);
+}
+
+pub fn model_summary(model: &Model, path: &str, size: u64) {
+    let (composition, normalization) = (model.composition(), model.normalization());
+
+    print_header("Model");
+
+    print_table(format!("File: {}", path), format!("Size: {} bytes", size));
+    print_table(format!("Version: {}", Model::VERSION), format!("Parameters: {}", model.parameter_count()));
+    println!();
+
+    print_subheader("Composition");
+
+    print_table(
+        format!("Input neurons: {}", composition[0]),
+        format!("Output neurons: {}", composition.last().unwrap())
+    );
+    println!("{:<50}\n", format!("Hidden neurons: {:?}", &composition[1..composition.len() - 1]));
+
+    println!("{:<50}", format!("Activations: {:?}", model.activations()));
+    println!("{:<50}\n", format!("Normalization: {} - {}", normalization.min, normalization.max));
+
+    print_end();
+}
@@ -1,4 +1,4 @@
-use super::{state::{ Network, HyperParams }, optimizations::regularization::Regularization };
+use super::state::{ Network, HyperParams };
 
 impl Network {
     fn compute_costs(&mut self, targets: &[f64]) {
@@ -6,93 +6,188 @@ impl Network {
 
         for ((cost, output), target) in self.costs[output_layer].iter_mut()
             .zip(self.outputs[output_layer].iter())
-            .zip(targets.iter()) 
+            .zip(targets.iter())
         {
             *cost = output - target;
         }
 
+        // Propagate the costs back through the dropout masks and activation functions of the next layer
         for layer in (0..output_layer).rev() {
-            for neuron in 0..self.outputs[layer].len() {               
+            let derivative = self.hyper_params.activations[layer + 1].derivative;
+
+            let next_layer_deltas: Vec<f64> = self.costs[layer + 1].iter()
+                .zip(self.net_inputs[layer + 1].iter())
+                .zip(self.dropout_mask[layer + 1].iter())
+                .map(|((cost, net_input), dropout_mask)| cost * derivative(*net_input) * dropout_mask)
+                .collect();
+
+            for neuron in 0..self.outputs[layer].len() {
                 self.costs[layer][neuron] = 0.0;
 
-                for (index, weights_prev_layer) in self.weights[layer + 1].iter().enumerate() {
-                    self.costs[layer][neuron] += weights_prev_layer[neuron] * self.costs[layer + 1][index];
+                for (weights_next_layer, delta) in self.weights[layer + 1].iter().zip(&next_layer_deltas) {
+                    self.costs[layer][neuron] += weights_next_layer[neuron] * delta;
                 }
-            }       
-        }    
+            }
+        }
     }
 
-    fn backward_pass(&mut self, layer: usize, inputs: &[f64]) {
-        let HyperParams { activations, learning_rate, optimizer, regularization, .. } = &self.hyper_params;
+    fn backward_pass(&mut self, layer: usize) {
+        let HyperParams { activations, regularization, .. } = &self.hyper_params;
 
+        // Inputs and outputs are dropped out in 'forward', so dropped out ones contribute no gradient
         let prev_layer_output = match layer == 0 {
-            true => inputs,
+            true => &self.inputs,
             false => &self.outputs[layer - 1]
         };
-        
-        for (((((((((weights, bias), net_input), cost), moment_1_weights), moment_2_weights), moment_1_bias), moment_2_bias), weight_updates), bias_update) in self.weights[layer].iter_mut()
-            .zip(self.biases[layer].iter_mut())
+
+        for ((((((weights, bias), net_input), cost), dropout_mask), weight_gradients), bias_gradient) in self.weights[layer].iter()
+            .zip(self.biases[layer].iter())
             .zip(self.net_inputs[layer].iter())
             .zip(self.costs[layer].iter())
-            .zip(self.optimizer.moment_1.weights[layer].iter_mut())
-            .zip(self.optimizer.moment_2.weights[layer].iter_mut())
-            .zip(self.optimizer.moment_1.biases[layer].iter_mut())
-            .zip(self.optimizer.moment_2.biases[layer].iter_mut())
-            .zip(self.batch.weight_updates[layer].iter_mut())
-            .zip(self.batch.bias_updates[layer].iter_mut())
+            .zip(self.dropout_mask[layer].iter())
+            .zip(self.gradients.weights[layer].iter_mut())
+            .zip(self.gradients.biases[layer].iter_mut())
         {
-            let slope = (activations[layer].derivative)(*net_input);
-            let current_weight_l2_norm = Regularization::compute_l2_norm(weights);
-                       
-            for ((((weight, prev_layer_output), moment_1_weight), moment_2_weight), weight_update) in weights.iter_mut()
+            let delta = cost * (activations[layer].derivative)(*net_input) * dropout_mask;
+
+            for ((weight, prev_layer_output), weight_gradient) in weights.iter()
                 .zip(prev_layer_output)
-                .zip(moment_1_weights.iter_mut())
-                .zip(moment_2_weights.iter_mut())
-                .zip(weight_updates.iter_mut())
+                .zip(weight_gradients.iter_mut())
             {
-                let gradient = 
-                    (cost + Self::elastic_net_regularization(&regularization.elastic_net.weights, *weight)) *
-                    slope *
-                    prev_layer_output;
+                *weight_gradient =
+                    delta * prev_layer_output +
+                    Self::elastic_net_gradient(&regularization.elastic_net.weights, *weight);
+            }
 
+            *bias_gradient =
+                delta +
+                Self::elastic_net_gradient(&regularization.elastic_net.biases, *bias);
+        }
+    }
 
-                let (corrected_moment_1, corrected_moment_2) = Self::compute_moments(
-                    moment_1_weight, moment_2_weight, optimizer, &gradient, &self.optimizer.iteration
-                );
+    // Stores the gradients of the sample of the last 'forward' in 'self.gradients'
+    pub(crate) fn backward(&mut self, targets: &[f64]) {
+        self.compute_costs(targets);
 
-                *weight_update += *weight 
-                    - learning_rate.alpha 
-                    * corrected_moment_1 
-                    / (corrected_moment_2.sqrt() 
-                    + optimizer.epsilon);
+        for layer in (0..self.outputs.len()).rev() {
+            self.backward_pass(layer);
+        }
+    }
+}
 
-                if current_weight_l2_norm > regularization.max_norm_constraint {
-                    *weight_update *= regularization.max_norm_constraint / current_weight_l2_norm
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Normalization,
+        network::{
+            utils,
+            optimizations::{
+                activations::ActivationType::*,
+                regularization::{ Dropout, ElasticNetRegularization, ElasticNetRegularizer }
             }
+        }
+    };
+
+    const STEP: f64 = 1e-6;
+    const TOLERANCE: f64 = 1e-6;
+
+    // Widening hidden layer, so every layer of the dropout masks is used
+    fn network() -> Network {
+        let mut hyper_params = HyperParams::inference(vec![3, 6, 4, 2], &[Tanh, Swish, Sigmoid], Normalization::default());
+        hyper_params.seed = Some(7);
+
+        Network::new(hyper_params).unwrap()
+    }
+
+    // Half the squared error of the outputs plus the elastic-net penalty, the cost whose gradient 'backward' computes
+    fn cost(network: &mut Network, inputs: &[f64], targets: &[f64]) -> f64 {
+        network.forward(inputs);
+
+        let error = network.outputs.last().unwrap().iter()
+            .zip(targets)
+            .map(|(output, target)| (output - target).powi(2) / 2.0)
+            .sum::<f64>();
+
+        error + network.compute_regularization_penalty()
+    }
+
+    // Weights followed by biases, in the order of 'utils::flatten'
+    fn parameter(network: &mut Network, index: usize) -> &mut f64 {
+        network.weights.iter_mut().flatten().flatten()
+            .chain(network.biases.iter_mut().flatten())
+            .nth(index)
+            .unwrap()
+    }
+
+    // Compares the gradients of 'backward' with central differences of the cost
+    fn check_gradients(network: &mut Network, inputs: &[f64], targets: &[f64]) {
+        network.forward(inputs);
+        network.backward(targets);
+
+        let gradients = utils::flatten(&network.gradients.weights, &network.gradients.biases)
+            .copied()
+            .collect::<Vec<f64>>();
+
+        for (index, gradient) in gradients.into_iter().enumerate() {
+            let value = *parameter(network, index);
 
-            let gradient = 
-                (cost + Self::elastic_net_regularization(&regularization.elastic_net.biases, *bias)) *
-                slope;
+            *parameter(network, index) = value + STEP;
+            let cost_plus = cost(network, inputs, targets);
 
+            *parameter(network, index) = value - STEP;
+            let cost_minus = cost(network, inputs, targets);
 
-            let (corrected_moment_1, corrected_moment_2) = Self::compute_moments(
-                moment_1_bias, moment_2_bias, optimizer, &gradient, &self.optimizer.iteration
+            *parameter(network, index) = value;
+
+            let numerical = (cost_plus - cost_minus) / (2.0 * STEP);
+
+            assert!(
+                (gradient - numerical).abs() < TOLERANCE,
+                "parameter {index}: backward {gradient}, finite difference {numerical}"
             );
+        }
+    }
 
-            *bias_update += *bias 
-                - learning_rate.alpha
-                * corrected_moment_1
-                / (corrected_moment_2.sqrt()
-                + optimizer.epsilon);
-        }        
+    #[test]
+    fn gradients_match_finite_differences() {
+        check_gradients(&mut network(), &[0.2, -0.7, 0.9], &[1.0, 0.0]);
     }
-    
-    pub fn backward(&mut self, inputs: &[f64], targets: &[f64]) {       
-        self.compute_costs(targets);        
 
-        for layer in (0..self.outputs.len()).rev() {
-            self.backward_pass(layer, inputs);
+    #[test]
+    fn gradients_match_finite_differences_with_elastic_net() {
+        let mut network = network();
+
+        // The biases start at 0, where the central difference of '|value|' matches the subgradient 0
+        network.hyper_params.regularization.elastic_net = ElasticNetRegularization {
+            weights: ElasticNetRegularizer { l1: 0.03, l2: 0.02 },
+            biases: ElasticNetRegularizer { l1: 0.01, l2: 0.05 }
+        };
+
+        check_gradients(&mut network, &[0.2, -0.7, 0.9], &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn gradients_match_finite_differences_with_dropout() {
+        let mut network = network();
+
+        // Inverse dropout at a rate of 0.5, one input and two hidden neurons are dropped out
+        network.input_dropout_mask = vec![2.0, 0.0, 2.0];
+        network.dropout_mask[0] = vec![2.0, 0.0, 2.0, 2.0, 2.0, 2.0];
+        network.dropout_mask[1] = vec![2.0, 2.0, 0.0, 2.0];
+
+        check_gradients(&mut network, &[0.2, -0.7, 0.9], &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn output_layer_is_never_dropped_out() {
+        let mut network = network();
+        network.hyper_params.regularization.dropout_rate = Dropout { input_layer: 0.9, hidden_layer: 0.9 };
+
+        for _ in 0..10 {
+            Dropout::generate_mask(&mut network);
+
+            assert!(network.dropout_mask.last().unwrap().iter().all(|&mask| mask == 1.0));
         }
     }
 }
@@ -0,0 +1,306 @@
+use super::{
+    optimizations::{ optimizer::{ self, Parameters }, early_stopping::BestParameters },
+    state::{ Network, HyperParams, SeededRng },
+    observer::Observer,
+    config::ConfigError,
+    utils
+};
+
+use rand::SeedableRng;
+use serde::{ Serialize, Deserialize };
+use std::{ fmt, fs, io, collections::HashMap };
+
+/// Write the complete training state every `interval` epochs, only used by mini-batch training.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Checkpoint {
+    /// Epochs between two writes
+    pub interval: u32,
+    /// File that is overwritten by every write
+    pub path: String
+}
+
+impl Checkpoint {
+    // Called at the end of every epoch
+    pub(crate) fn update(network: &Network, observer: &mut dyn Observer) {
+        if let Some(Checkpoint { interval, path }) = &network.hyper_params.checkpoint {
+            // A failed write is reported and training continues, the next interval tries again
+            if network.epoch.is_multiple_of(*interval) {
+                if let Err(error) = network.save_checkpoint(path) {
+                    observer.checkpoint_failed(path, &error);
+                }
+            }
+        }
+    }
+}
+
+/// Errors of writing and resuming checkpoints.
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The checkpoint file could not be read or written
+    Io(io::Error),
+    /// Number of a line that is neither a section header nor a number inside a section
+    InvalidLine(usize),
+    /// Name of a section the checkpoint lacks
+    MissingSection(String),
+    /// The section was written with different hyperparameters
+    SectionMismatch(String),
+    /// The hyperparameters the checkpoint is resumed with are invalid
+    InvalidHyperParams(ConfigError)
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "checkpoint file: {error}"),
+            Self::InvalidLine(line) => write!(f, "line {line} is neither a section header nor a number inside a section"),
+            Self::MissingSection(name) => write!(f, "checkpoint is missing section '{name}'"),
+            Self::SectionMismatch(name) => write!(f, "section '{name}' does not match the hyperparameters"),
+            Self::InvalidHyperParams(error) => write!(f, "{error}")
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ConfigError> for CheckpointError {
+    fn from(error: ConfigError) -> Self {
+        Self::InvalidHyperParams(error)
+    }
+}
+
+type Sections = HashMap<String, Vec<f64>>;
+
+// Values of a section in the order they were written, 'length' is checked before anything is read,
+// so reading the section can neither run out of values nor leave values over
+fn take_section(
+    sections: &mut Sections,
+    name: &str,
+    length: impl Fn(usize) -> bool
+) -> Result<std::vec::IntoIter<f64>, CheckpointError> {
+    let values = sections.remove(name).ok_or_else(|| CheckpointError::MissingSection(name.to_string()))?;
+
+    match length(values.len()) {
+        true => Ok(values.into_iter()),
+        false => Err(CheckpointError::SectionMismatch(name.to_string()))
+    }
+}
+
+// Split into 32 bit chunks, larger integers are not exactly representable as f64
+fn split_integer(value: u128, chunks: usize) -> impl Iterator<Item = f64> {
+    (0..chunks).rev().map(move |chunk| ((value >> (32 * chunk)) & 0xffff_ffff) as f64)
+}
+
+fn join_integer(values: &mut dyn Iterator<Item = f64>, chunks: usize) -> u128 {
+    (0..chunks).fold(0, |value, _| (value << 32) | optimizer::read_value(values) as u128)
+}
+
+fn read_parameters(values: &mut dyn Iterator<Item = f64>, composition: &[usize]) -> Parameters {
+    let mut parameters = Parameters::new(composition);
+    parameters.read(values);
+
+    parameters
+}
+
+impl Network {
+    /// Writes the complete training state, see [`Network::resume`].
+    pub fn save_checkpoint(&self, path: &str) -> Result<(), CheckpointError> {
+        let mut content = String::new();
+
+        let mut write = |name: &str, values: &mut dyn Iterator<Item = f64>| {
+            content.push_str(&format!("[{name}]\n"));
+
+            for value in values {
+                content.push_str(&format!("{value}\n"));
+            }
+        };
+
+        write("epoch", &mut [self.epoch as f64].into_iter());
+        // Seed and position in the stream of the random number generator
+        write("rng", &mut split_integer(self.seed as u128, 2).chain(split_integer(self.rng.get_word_pos(), 4)));
+        write("alpha", &mut [self.hyper_params.learning_rate.alpha].into_iter());
+        write("parameters", &mut utils::flatten(&self.weights, &self.biases).copied());
+        write("optimizer", &mut self.optimizer.state().into_iter());
+        write("scheduler", &mut self.scheduler.state().into_iter());
+        write("performance", &mut self.performance.iter().copied());
+
+        if let Some(ema) = &self.averages.ema {
+            write("ema", &mut ema.iter().copied());
+        }
+
+        write("swa", &mut self.averages.swa.iter().flat_map(|snapshot| snapshot.iter().copied()));
+
+        if let Some(best) = &self.best {
+            write("best", &mut [best.epoch as f64, best.score].into_iter().chain(best.parameters.iter().copied()));
+        }
+
+        // Write into a temporary file first, so an interrupted write keeps the previous checkpoint intact
+        let temporary_path = format!("{path}.tmp");
+
+        fs::write(&temporary_path, content)?;
+        fs::rename(&temporary_path, path)?;
+
+        Ok(())
+    }
+
+    /// Initializes the network from the hyperparameters and continues the training state of the checkpoint.
+    pub fn resume(hyper_params: HyperParams, path: &str) -> Result<Self, CheckpointError> {
+        let mut sections = Sections::new();
+        let mut section = None;
+
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            match line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                Some(name) => {
+                    sections.insert(name.to_string(), Vec::new());
+                    section = Some(name.to_string());
+                },
+                None => {
+                    let values = section.as_ref().and_then(|name| sections.get_mut(name));
+                    let value = line.trim().parse::<f64>().ok();
+
+                    match (values, value) {
+                        (Some(values), Some(value)) => values.push(value),
+                        _ => return Err(CheckpointError::InvalidLine(index + 1))
+                    }
+                }
+            }
+        }
+
+        let mut network = Self::new(hyper_params)?;
+        let composition = network.hyper_params.composition.clone();
+        let parameter_count = network.parameter_count();
+
+        network.epoch = optimizer::read_value(&mut take_section(&mut sections, "epoch", |length| length == 1)?) as u32;
+
+        let values = &mut take_section(&mut sections, "rng", |length| length == 6)?;
+        network.seed = join_integer(values, 2) as u64;
+        network.rng = SeededRng::seed_from_u64(network.seed);
+        network.rng.set_word_pos(join_integer(values, 4));
+
+        network.hyper_params.learning_rate.alpha = optimizer::read_value(
+            &mut take_section(&mut sections, "alpha", |length| length == 1)?
+        );
+
+        let values = &mut take_section(&mut sections, "parameters", |length| length == parameter_count)?;
+        let Parameters { weights, biases } = read_parameters(values, &composition);
+
+        network.weights = weights;
+        network.biases = biases;
+
+        // Optimizer and scheduler states have a fixed length for given hyperparameters,
+        // so the states of the new network give the expected lengths
+        let optimizer_length = network.optimizer.state().len();
+        network.optimizer.set_state(&mut take_section(&mut sections, "optimizer", |length| length == optimizer_length)?);
+
+        let scheduler_length = network.scheduler.state().len();
+        network.scheduler.set_state(&mut take_section(&mut sections, "scheduler", |length| length == scheduler_length)?);
+
+        network.performance = take_section(&mut sections, "performance", |_| true)?.collect();
+
+        if sections.contains_key("ema") {
+            let values = &mut take_section(&mut sections, "ema", |length| length == parameter_count)?;
+            network.averages.ema = Some(read_parameters(values, &composition));
+        }
+
+        let values = &mut take_section(&mut sections, "swa", |length| length % parameter_count == 0)?.peekable();
+
+        while values.peek().is_some() {
+            network.averages.swa.push_back(read_parameters(values, &composition));
+        }
+
+        if sections.contains_key("best") {
+            let values = &mut take_section(&mut sections, "best", |length| length == parameter_count + 2)?;
+
+            network.best = Some(BestParameters {
+                epoch: optimizer::read_value(values) as u32,
+                score: optimizer::read_value(values),
+                parameters: read_parameters(values, &composition)
+            });
+        }
+
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::{ Data, Normalization },
+        network::optimizations::{
+            activations::ActivationType::*,
+            adam::AdamHyperParams,
+            lookahead::LookaheadHyperParams,
+            optimizer::OptimizerType,
+            regularization::Dropout
+        }
+    };
+
+    // Adam wrapped by Lookahead with dropout, so every section that changes while training is written
+    fn hyper_params() -> HyperParams {
+        let mut hyper_params = HyperParams::inference(vec![4, 6, 3], &[Tanh, Sigmoid], Normalization::GRAYSCALE);
+
+        hyper_params.regularization.dropout_rate = Dropout { input_layer: 0.1, hidden_layer: 0.2 };
+        hyper_params.learning_rate.alpha = 0.05;
+        hyper_params.optimizer = OptimizerType::Adam(AdamHyperParams {
+            beta_1: 0.9,
+            beta_2: 0.999,
+            epsilon: 1e-8,
+            amsgrad: false
+        });
+        hyper_params.lookahead = Some(LookaheadHyperParams { steps: 3, alpha: 0.5 });
+        hyper_params.batch_size = 2;
+        hyper_params.seed = Some(11);
+
+        hyper_params
+    }
+
+    // Four steps per epoch, so a checkpoint after two epochs falls between two Lookahead synchronizations
+    fn data() -> Data {
+        Data {
+            inputs: (0..8).map(|sample| (0..4).map(|pixel| ((sample * 4 + pixel) % 5) as f64 / 4.0).collect()).collect(),
+            targets: (0..8).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    #[test]
+    fn resumed_training_matches_uninterrupted_training() {
+        let path = std::env::temp_dir()
+            .join(format!("digit-recognition-{}-checkpoint.txt", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+
+        let data = data();
+
+        let mut uninterrupted = Network::new(hyper_params()).unwrap();
+
+        for epoch in 1..=4 {
+            uninterrupted.train_epoch(&data, epoch);
+        }
+
+        let mut interrupted = Network::new(hyper_params()).unwrap();
+
+        for epoch in 1..=2 {
+            interrupted.train_epoch(&data, epoch);
+        }
+
+        interrupted.save_checkpoint(&path).unwrap();
+        let resumed = Network::resume(hyper_params(), &path);
+        fs::remove_file(&path).unwrap();
+
+        let mut resumed = resumed.unwrap();
+
+        assert_eq!(resumed.epoch, 2);
+
+        for epoch in 3..=4 {
+            resumed.train_epoch(&data, epoch);
+        }
+
+        assert_eq!(resumed.weights, uninterrupted.weights);
+        assert_eq!(resumed.biases, uninterrupted.biases);
+    }
+}
@@ -0,0 +1,684 @@
+use super::{
+    optimizations::{
+        regularization::{ ElasticNetRegularizer, MaxNormConstraint },
+        learning_rate::{ SchedulerType, Decay, Restart, OneCycleHyperParams, ReduceOnPlateauHyperParams },
+        optimizer::OptimizerType,
+        gradient_clipping::GradientClipping,
+        early_stopping::{ Criterion, BestModel },
+        weight_averaging::{ WeightAveraging, SwaSchedule, Averaging },
+        lr_finder::LrFinderHyperParams
+    },
+    search::{ Search, SearchSpace, SearchStrategy, Range },
+    population::PopulationBasedTraining,
+    state::{ HyperParams, TrainingMode }
+};
+
+use serde::de::DeserializeOwned;
+use std::{ fmt, fs, io, path::Path };
+
+/// Errors of loading, saving and validating hyperparameters.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read or written
+    Io(io::Error),
+    /// Path of a file that is neither a .toml nor a .json file
+    UnsupportedFormat(String),
+    /// The content does not match the expected fields
+    Parse(String),
+    /// The values can not be written in the format of the file
+    Serialize(String),
+    /// A value violates a requirement
+    Invalid {
+        /// Path of the field, e.g. 'learning_rate.alpha'
+        field: String,
+        /// Violated requirement
+        reason: String
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "config file: {error}"),
+            Self::UnsupportedFormat(path) => write!(f, "unsupported config file '{path}', expected a .toml or .json file"),
+            Self::Parse(error) => write!(f, "parsing config file: {error}"),
+            Self::Serialize(error) => write!(f, "writing config file: {error}"),
+            Self::Invalid { field, reason } => write!(f, "invalid hyperparameter '{field}': {reason}")
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Format of a config file.
+#[derive(Clone, Copy, Debug)]
+pub enum ConfigFormat {
+    /// '.toml' files
+    Toml,
+    /// '.json' files
+    Json
+}
+
+impl ConfigFormat {
+    /// Chosen by the file extension.
+    pub fn get(path: &str) -> Result<Self, ConfigError> {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            _ => Err(ConfigError::UnsupportedFormat(path.to_string()))
+        }
+    }
+}
+
+// Deserializes a TOML or JSON file, chosen by the file extension
+fn parse<T: DeserializeOwned>(path: &str) -> Result<T, ConfigError> {
+    let format = ConfigFormat::get(path)?;
+    let content = fs::read_to_string(path)?;
+
+    match format {
+        ConfigFormat::Toml => toml::from_str(&content).map_err(|error| ConfigError::Parse(error.to_string())),
+        ConfigFormat::Json => serde_json::from_str(&content).map_err(|error| ConfigError::Parse(error.to_string()))
+    }
+}
+
+fn check(condition: bool, field: &str, reason: &str) -> Result<(), ConfigError> {
+    match condition {
+        true => Ok(()),
+        false => Err(ConfigError::Invalid { field: field.to_string(), reason: reason.to_string() })
+    }
+}
+
+fn check_positive(value: f64, field: &str) -> Result<(), ConfigError> {
+    check(value > 0.0 && value.is_finite(), field, "must be positive")
+}
+
+fn check_non_negative(value: f64, field: &str) -> Result<(), ConfigError> {
+    check(value >= 0.0 && value.is_finite(), field, "must not be negative")
+}
+
+// Rates and decay factors, e.g. dropout rates and momentum terms
+fn check_fraction(value: f64, field: &str) -> Result<(), ConfigError> {
+    check((0.0..1.0).contains(&value), field, "must be at least 0 and less than 1")
+}
+
+fn check_elastic_net(ElasticNetRegularizer { l1, l2 }: &ElasticNetRegularizer, field: &str) -> Result<(), ConfigError> {
+    check_non_negative(*l1, &format!("{field}.l1"))?;
+    check_non_negative(*l2, &format!("{field}.l2"))
+}
+
+// Both bounds have to pass 'check_value', the check of the sampled hyperparameter
+fn check_range(range: Range, field: &str, check_value: fn(f64, &str) -> Result<(), ConfigError>) -> Result<(), ConfigError> {
+    let (Range::Uniform { min, max } | Range::LogUniform { min, max }) = range;
+
+    check_value(min, &format!("{field}.min"))?;
+    check_value(max, &format!("{field}.max"))?;
+    check(min <= max, field, "'min' must not be greater than 'max'")?;
+
+    match range {
+        Range::LogUniform { .. } => check(min > 0.0, &format!("{field}.min"), "must be positive for a log-uniform range"),
+        Range::Uniform { .. } => Ok(())
+    }
+}
+
+impl HyperParams {
+    /// Reads and validates a TOML or JSON config file, chosen by the file extension.
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let hyper_params: Self = parse(path)?;
+
+        hyper_params.validate()?;
+
+        Ok(hyper_params)
+    }
+
+    /// Writes a TOML or JSON config file, chosen by the file extension.
+    pub fn save(&self, path: &str) -> Result<(), ConfigError> {
+        let content = match ConfigFormat::get(path)? {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(|error| ConfigError::Serialize(error.to_string()))?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(|error| ConfigError::Serialize(error.to_string()))?
+        };
+
+        fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    /// Checked when loading a config file and when creating a network.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let composition = &self.composition;
+
+        check(composition.len() >= 2, "composition", "requires at least an input and an output layer")?;
+        check(composition.iter().all(|&neurons| neurons > 0), "composition", "every layer requires at least one neuron")?;
+        check(
+            self.activations.len() == composition.len() - 1,
+            "activations",
+            "requires one activation function for each hidden and output layer"
+        )?;
+
+        self.validate_regularization()?;
+        self.validate_learning_rate()?;
+        self.validate_optimizer()?;
+
+        let GradientClipping { value, norm } = self.gradient_clipping;
+
+        if let Some(value) = value {
+            check_positive(value, "gradient_clipping.value")?;
+        }
+
+        if let Some(norm) = norm {
+            check_positive(norm, "gradient_clipping.norm")?;
+        }
+
+        check(self.batch_size > 0, "batch_size", "must be at least 1")?;
+
+        self.validate_early_stopping()?;
+        self.validate_weight_averaging()?;
+
+        if let TrainingMode::Lbfgs(lbfgs) = &self.training_mode {
+            check(lbfgs.history_size > 0, "training_mode.Lbfgs.history_size", "must be at least 1")?;
+            check(lbfgs.max_iterations > 0, "training_mode.Lbfgs.max_iterations", "must be at least 1")?;
+            check_non_negative(lbfgs.tolerance, "training_mode.Lbfgs.tolerance")?;
+
+            self.validate_lbfgs()?;
+        }
+
+        if let Some(checkpoint) = &self.checkpoint {
+            check(checkpoint.interval > 0, "checkpoint.interval", "must be at least 1")?;
+            check(!checkpoint.path.is_empty(), "checkpoint.path", "must not be empty")?;
+        }
+
+        check(
+            self.normalization.max > self.normalization.min,
+            "normalization",
+            "'max' must be greater than 'min'"
+        )
+    }
+
+    /// Input and output layer have to match the samples of the dataset.
+    pub fn validate_dimensions(&self, inputs: usize, outputs: usize) -> Result<(), ConfigError> {
+        let composition = &self.composition;
+
+        check(composition[0] == inputs, "composition", &format!("the input layer requires {inputs} neurons"))?;
+        check(composition[composition.len() - 1] == outputs, "composition", &format!("the output layer requires {outputs} neurons"))
+    }
+
+    fn validate_regularization(&self) -> Result<(), ConfigError> {
+        let regularization = &self.regularization;
+
+        check_elastic_net(&regularization.elastic_net.weights, "regularization.elastic_net.weights")?;
+        check_elastic_net(&regularization.elastic_net.biases, "regularization.elastic_net.biases")?;
+
+        check_fraction(regularization.dropout_rate.input_layer, "regularization.dropout_rate.input_layer")?;
+        check_fraction(regularization.dropout_rate.hidden_layer, "regularization.dropout_rate.hidden_layer")?;
+
+        let max_norm_constraint = &regularization.max_norm_constraint;
+        check(max_norm_constraint.limit > 0.0, "regularization.max_norm_constraint.limit", "must be positive")?;
+
+        if let Some(layer_limits) = &max_norm_constraint.layer_limits {
+            check(
+                layer_limits.len() == self.composition.len() - 1,
+                "regularization.max_norm_constraint.layer_limits",
+                "requires one limit for each hidden and output layer"
+            )?;
+            check(
+                layer_limits.iter().all(|&limit| limit > 0.0),
+                "regularization.max_norm_constraint.layer_limits",
+                "every limit must be positive"
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_learning_rate(&self) -> Result<(), ConfigError> {
+        let learning_rate = &self.learning_rate;
+
+        // 0 is allowed for networks that are only used for predictions
+        check_non_negative(learning_rate.alpha, "learning_rate.alpha")?;
+
+        match &learning_rate.scheduler {
+            SchedulerType::Constant => {},
+            SchedulerType::Decay { decay, restart } => {
+                if let Some(Decay { rate, step, .. }) = decay {
+                    check_positive(*rate, "learning_rate.scheduler.Decay.decay.rate")?;
+                    check(*step > 0, "learning_rate.scheduler.Decay.decay.step", "must be at least 1")?;
+                }
+
+                if let Some(Restart { interval, alpha }) = restart {
+                    check(*interval > 0, "learning_rate.scheduler.Decay.restart.interval", "must be at least 1")?;
+                    check_positive(*alpha, "learning_rate.scheduler.Decay.restart.alpha")?;
+                }
+            },
+            SchedulerType::CosineAnnealing(hyper_params) => {
+                check_non_negative(hyper_params.min_alpha, "learning_rate.scheduler.CosineAnnealing.min_alpha")?;
+                check(hyper_params.period > 0, "learning_rate.scheduler.CosineAnnealing.period", "must be at least 1")?;
+                check(
+                    hyper_params.period_multiplier > 0,
+                    "learning_rate.scheduler.CosineAnnealing.period_multiplier",
+                    "must be at least 1"
+                )?;
+            },
+            SchedulerType::OneCycle(OneCycleHyperParams { max_alpha, epochs, warmup_fraction, final_divisor }) => {
+                check_positive(*max_alpha, "learning_rate.scheduler.OneCycle.max_alpha")?;
+                check(*epochs > 0, "learning_rate.scheduler.OneCycle.epochs", "must be at least 1")?;
+                check(
+                    *warmup_fraction > 0.0 && *warmup_fraction < 1.0,
+                    "learning_rate.scheduler.OneCycle.warmup_fraction",
+                    "must be greater than 0 and less than 1"
+                )?;
+                check_positive(*final_divisor, "learning_rate.scheduler.OneCycle.final_divisor")?;
+            },
+            SchedulerType::Triangular(hyper_params) => {
+                check_positive(hyper_params.max_alpha, "learning_rate.scheduler.Triangular.max_alpha")?;
+                check(hyper_params.half_period > 0, "learning_rate.scheduler.Triangular.half_period", "must be at least 1")?;
+            },
+            SchedulerType::ReduceOnPlateau(ReduceOnPlateauHyperParams { factor, min_delta, min_alpha, .. }) => {
+                check(
+                    *factor > 0.0 && *factor < 1.0,
+                    "learning_rate.scheduler.ReduceOnPlateau.factor",
+                    "must be greater than 0 and less than 1"
+                )?;
+                check_non_negative(*min_delta, "learning_rate.scheduler.ReduceOnPlateau.min_delta")?;
+                check_non_negative(*min_alpha, "learning_rate.scheduler.ReduceOnPlateau.min_alpha")?;
+            }
+        }
+
+        if let Some(warmup) = learning_rate.warmup {
+            check(warmup.steps > 0, "learning_rate.warmup.steps", "must be at least 1")?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_optimizer(&self) -> Result<(), ConfigError> {
+        match &self.optimizer {
+            OptimizerType::Sgd => {},
+            OptimizerType::Momentum(hyper_params) => check_fraction(hyper_params.beta, "optimizer.Momentum.beta")?,
+            OptimizerType::Nesterov(hyper_params) => check_fraction(hyper_params.beta, "optimizer.Nesterov.beta")?,
+            OptimizerType::RmsProp(hyper_params) => {
+                check_fraction(hyper_params.beta, "optimizer.RmsProp.beta")?;
+                check_positive(hyper_params.epsilon, "optimizer.RmsProp.epsilon")?;
+            },
+            OptimizerType::AdaGrad(hyper_params) => check_positive(hyper_params.epsilon, "optimizer.AdaGrad.epsilon")?,
+            OptimizerType::Adam(hyper_params) => {
+                check_fraction(hyper_params.beta_1, "optimizer.Adam.beta_1")?;
+                check_fraction(hyper_params.beta_2, "optimizer.Adam.beta_2")?;
+                check_positive(hyper_params.epsilon, "optimizer.Adam.epsilon")?;
+            },
+            OptimizerType::AdamW(hyper_params) => {
+                check_fraction(hyper_params.adam.beta_1, "optimizer.AdamW.adam.beta_1")?;
+                check_fraction(hyper_params.adam.beta_2, "optimizer.AdamW.adam.beta_2")?;
+                check_positive(hyper_params.adam.epsilon, "optimizer.AdamW.adam.epsilon")?;
+                check_non_negative(hyper_params.weight_decay, "optimizer.AdamW.weight_decay")?;
+            },
+            OptimizerType::Lamb(hyper_params) => {
+                check_fraction(hyper_params.beta_1, "optimizer.Lamb.beta_1")?;
+                check_fraction(hyper_params.beta_2, "optimizer.Lamb.beta_2")?;
+                check_positive(hyper_params.epsilon, "optimizer.Lamb.epsilon")?;
+                check_non_negative(hyper_params.weight_decay, "optimizer.Lamb.weight_decay")?;
+            },
+            OptimizerType::Lars(hyper_params) => {
+                check_fraction(hyper_params.momentum, "optimizer.Lars.momentum")?;
+                check_non_negative(hyper_params.weight_decay, "optimizer.Lars.weight_decay")?;
+                check_positive(hyper_params.trust_coefficient, "optimizer.Lars.trust_coefficient")?;
+            }
+        }
+
+        if let Some(lookahead) = self.lookahead {
+            check(lookahead.steps > 0, "lookahead.steps", "must be at least 1")?;
+            check(
+                lookahead.alpha > 0.0 && lookahead.alpha <= 1.0,
+                "lookahead.alpha",
+                "must be greater than 0 and at most 1"
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // L-BFGS stops after 'max_iterations' or once converged and never checkpoints or averages parameters
+    fn validate_lbfgs(&self) -> Result<(), ConfigError> {
+        let unsupported = "is not supported with 'training_mode.Lbfgs'";
+
+        check(self.checkpoint.is_none(), "checkpoint", unsupported)?;
+        check(self.early_stopping.max_epochs.is_none(), "early_stopping.max_epochs", unsupported)?;
+
+        // Steps are taken along the line search direction, clipping or projecting them would break its conditions
+        let GradientClipping { value, norm } = &self.gradient_clipping;
+
+        check(value.is_none(), "gradient_clipping.value", unsupported)?;
+        check(norm.is_none(), "gradient_clipping.norm", unsupported)?;
+
+        let MaxNormConstraint { limit, layer_limits } = &self.regularization.max_norm_constraint;
+
+        check(*limit == f64::INFINITY, "regularization.max_norm_constraint.limit", "must be 'inf' with 'training_mode.Lbfgs'")?;
+        check(layer_limits.is_none(), "regularization.max_norm_constraint.layer_limits", unsupported)?;
+
+        if let Criterion::Best(BestModel { restore_best, .. }) = &self.early_stopping.criterion {
+            check(!restore_best, "early_stopping.criterion.Best.restore_best", unsupported)?;
+        }
+
+        let WeightAveraging { ema, swa, apply } = &self.weight_averaging;
+
+        check(ema.is_none(), "weight_averaging.ema", unsupported)?;
+        check(swa.is_none(), "weight_averaging.swa", unsupported)?;
+        check(apply.is_none(), "weight_averaging.apply", unsupported)
+    }
+
+    fn validate_early_stopping(&self) -> Result<(), ConfigError> {
+        match &self.early_stopping.criterion {
+            Criterion::Stability { stability_threshold, patience } => {
+                check_non_negative(*stability_threshold, "early_stopping.criterion.Stability.stability_threshold")?;
+                check(*patience > 0, "early_stopping.criterion.Stability.patience", "must be at least 1")?;
+            },
+            Criterion::Best(BestModel { min_delta, restore_best, .. }) => {
+                check_non_negative(*min_delta, "early_stopping.criterion.Best.min_delta")?;
+                check(
+                    !restore_best || self.weight_averaging.apply.is_none(),
+                    "early_stopping.criterion.Best.restore_best",
+                    "restoring the best parameters and applying averaged parameters are exclusive"
+                )?;
+            }
+        }
+
+        if let Some(max_epochs) = self.early_stopping.max_epochs {
+            check(max_epochs > 0, "early_stopping.max_epochs", "must be at least 1")?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_weight_averaging(&self) -> Result<(), ConfigError> {
+        let weight_averaging = &self.weight_averaging;
+
+        if let Some(ema) = weight_averaging.ema {
+            check(ema.decay > 0.0 && ema.decay < 1.0, "weight_averaging.ema.decay", "must be greater than 0 and less than 1")?;
+        }
+
+        match weight_averaging.swa {
+            Some(SwaSchedule::Epochs(count)) => check(count > 0, "weight_averaging.swa.Epochs", "must be at least 1")?,
+            Some(SwaSchedule::Cycles(count)) => {
+                check(count > 0, "weight_averaging.swa.Cycles", "must be at least 1")?;
+                check(
+                    self.learning_rate.scheduler.is_cyclic(),
+                    "weight_averaging.swa.Cycles",
+                    "averaging over cycles requires a cyclic learning rate scheduler"
+                )?;
+            },
+            None => {}
+        }
+
+        match weight_averaging.apply {
+            Some(Averaging::Ema) => check(weight_averaging.ema.is_some(), "weight_averaging.apply", "requires 'ema'"),
+            Some(Averaging::Swa) => check(weight_averaging.swa.is_some(), "weight_averaging.apply", "requires 'swa'"),
+            None => Ok(())
+        }
+    }
+}
+
+impl LrFinderHyperParams {
+    /// Checked before a range test, which grows the learning rate from 'min_alpha' to 'max_alpha'.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_positive(self.min_alpha, "min_alpha")?;
+        check(self.max_alpha > self.min_alpha && self.max_alpha.is_finite(), "max_alpha", "must be greater than 'min_alpha'")?;
+        check(self.steps >= 2, "steps", "must be at least 2")?;
+        check_fraction(self.smoothing, "smoothing")
+    }
+}
+
+impl SearchSpace {
+    fn validate(&self, field: &str) -> Result<(), ConfigError> {
+        if let Some(hidden_layers) = &self.hidden_layers {
+            check(!hidden_layers.is_empty(), &format!("{field}.hidden_layers"), "must not be empty")?;
+            check(
+                hidden_layers.iter().flatten().all(|&neurons| neurons > 0),
+                &format!("{field}.hidden_layers"),
+                "every layer requires at least one neuron"
+            )?;
+        }
+
+        if let Some(hidden_activation) = &self.hidden_activation {
+            check(!hidden_activation.is_empty(), &format!("{field}.hidden_activation"), "must not be empty")?;
+        }
+
+        if let Some(alpha) = self.alpha {
+            check_range(alpha, &format!("{field}.alpha"), check_positive)?;
+        }
+
+        if let Some(input_dropout) = self.input_dropout {
+            check_range(input_dropout, &format!("{field}.input_dropout"), check_fraction)?;
+        }
+
+        if let Some(hidden_dropout) = self.hidden_dropout {
+            check_range(hidden_dropout, &format!("{field}.hidden_dropout"), check_fraction)?;
+        }
+
+        if let Some(l1) = self.l1 {
+            check_range(l1, &format!("{field}.l1"), check_non_negative)?;
+        }
+
+        if let Some(l2) = self.l2 {
+            check_range(l2, &format!("{field}.l2"), check_non_negative)?;
+        }
+
+        if let Some(batch_size) = &self.batch_size {
+            check(!batch_size.is_empty(), &format!("{field}.batch_size"), "must not be empty")?;
+            check(batch_size.iter().all(|&size| size > 0), &format!("{field}.batch_size"), "every size must be at least 1")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Search {
+    /// Reads and validates a TOML or JSON search config file, chosen by the file extension.
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let search: Self = parse(path)?;
+
+        search.validate()?;
+
+        Ok(search)
+    }
+
+    /// Checked when loading a search config file and before searching.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.space.validate("space")?;
+
+        match self.strategy {
+            SearchStrategy::Random { trials, epochs } => {
+                check(trials > 0, "strategy.Random.trials", "must be at least 1")?;
+                check(epochs > 0, "strategy.Random.epochs", "must be at least 1")
+            },
+            SearchStrategy::Hyperband { max_epochs, reduction_factor } => {
+                check(max_epochs > 0, "strategy.Hyperband.max_epochs", "must be at least 1")?;
+                check(reduction_factor >= 2, "strategy.Hyperband.reduction_factor", "must be at least 2")
+            }
+        }
+    }
+
+    /// The hyperparameters the space is sampled around have to train with mini-batches.
+    pub fn validate_base(&self, base: &HyperParams) -> Result<(), ConfigError> {
+        check(
+            matches!(base.training_mode, TrainingMode::MiniBatch),
+            "training_mode",
+            "hyperparameter search requires 'MiniBatch'"
+        )
+    }
+}
+
+impl PopulationBasedTraining {
+    /// Reads and validates a TOML or JSON population config file, chosen by the file extension.
+    pub fn load(path: &str) -> Result<Self, ConfigError> {
+        let pbt: Self = parse(path)?;
+
+        pbt.validate()?;
+
+        Ok(pbt)
+    }
+
+    /// Checked when loading a population config file and before training.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check(self.population >= 2, "population", "must be at least 2")?;
+        check(self.epochs > 0, "epochs", "must be at least 1")?;
+        check(self.interval > 0, "interval", "must be at least 1")?;
+        check_fraction(self.exploit_fraction, "exploit_fraction")?;
+        check_fraction(self.perturbation, "perturbation")?;
+
+        self.space.validate("space")
+    }
+
+    /// The members perturb a constant learning rate, a schedule would restart with every exploited copy.
+    pub fn validate_base(&self, base: &HyperParams) -> Result<(), ConfigError> {
+        let unsupported = "is not supported with population based training";
+
+        check(
+            matches!(base.training_mode, TrainingMode::MiniBatch),
+            "training_mode",
+            "population based training requires 'MiniBatch'"
+        )?;
+        check(
+            matches!(base.learning_rate.scheduler, SchedulerType::Constant),
+            "learning_rate.scheduler",
+            "population based training requires 'Constant'"
+        )?;
+        check(base.learning_rate.warmup.is_none(), "learning_rate.warmup", unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Normalization,
+        network::{
+            checkpoint::Checkpoint,
+            optimizations::{
+                activations::ActivationType::*,
+                adam::AdamHyperParams,
+                learning_rate::Warmup,
+                weight_averaging::Ema,
+                lbfgs::LbfgsHyperParams
+            }
+        }
+    };
+
+    fn hyper_params() -> HyperParams {
+        let mut hyper_params = HyperParams::inference(vec![4, 6, 3], &[LeakyRelu, Sigmoid], Normalization::GRAYSCALE);
+
+        hyper_params.regularization.max_norm_constraint.limit = 4.0;
+        hyper_params.learning_rate.alpha = 0.01;
+        hyper_params.learning_rate.scheduler = SchedulerType::OneCycle(OneCycleHyperParams {
+            max_alpha: 0.1,
+            epochs: 10,
+            warmup_fraction: 0.3,
+            final_divisor: 100.0
+        });
+        hyper_params.optimizer = OptimizerType::Adam(AdamHyperParams { beta_1: 0.9, beta_2: 0.999, epsilon: 1e-8, amsgrad: true });
+        hyper_params.checkpoint = Some(Checkpoint { interval: 2, path: String::from("checkpoint.txt") });
+        hyper_params.seed = Some(23);
+
+        hyper_params
+    }
+
+    fn path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("digit-recognition-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    // Makes valid hyperparameters invalid
+    type Change = fn(&mut HyperParams);
+
+    fn invalid_field(hyper_params: &HyperParams) -> Option<String> {
+        match hyper_params.validate() {
+            Err(ConfigError::Invalid { field, .. }) => Some(field),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_hyperparameters() {
+        let cases: [(Change, &str); 10] = [
+            (|hyper_params| hyper_params.composition = vec![4], "composition"),
+            (|hyper_params| hyper_params.activations.truncate(1), "activations"),
+            (|hyper_params| hyper_params.regularization.dropout_rate.hidden_layer = 1.0, "regularization.dropout_rate.hidden_layer"),
+            (|hyper_params| hyper_params.regularization.elastic_net.weights.l2 = -1.0, "regularization.elastic_net.weights.l2"),
+            (|hyper_params| hyper_params.learning_rate.alpha = f64::NAN, "learning_rate.alpha"),
+            (|hyper_params| hyper_params.learning_rate.warmup = Some(Warmup { steps: 0 }), "learning_rate.warmup.steps"),
+            (|hyper_params| hyper_params.batch_size = 0, "batch_size"),
+            (|hyper_params| hyper_params.weight_averaging.apply = Some(Averaging::Ema), "weight_averaging.apply"),
+            (|hyper_params| hyper_params.checkpoint = Some(Checkpoint { interval: 0, path: String::new() }), "checkpoint.interval"),
+            (
+                |hyper_params| hyper_params.training_mode = TrainingMode::Lbfgs(LbfgsHyperParams {
+                    history_size: 5,
+                    max_iterations: 10,
+                    tolerance: 1e-6
+                }),
+                "checkpoint"
+            )
+        ];
+
+        assert_eq!(invalid_field(&hyper_params()), None);
+
+        for (change, field) in cases {
+            let mut hyper_params = hyper_params();
+            change(&mut hyper_params);
+
+            assert_eq!(invalid_field(&hyper_params).as_deref(), Some(field));
+        }
+    }
+
+    #[test]
+    fn weight_averaging_requires_its_schedule() {
+        let mut hyper_params = hyper_params();
+        hyper_params.weight_averaging.ema = Some(Ema { decay: 0.99 });
+        hyper_params.weight_averaging.apply = Some(Averaging::Ema);
+
+        assert_eq!(invalid_field(&hyper_params), None);
+
+        hyper_params.weight_averaging.swa = Some(SwaSchedule::Cycles(2));
+
+        assert_eq!(invalid_field(&hyper_params).as_deref(), Some("weight_averaging.swa.Cycles"));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let hyper_params = hyper_params();
+
+        for name in ["config.toml", "config.json"] {
+            let path = path(name);
+
+            hyper_params.save(&path).unwrap();
+            let loaded = HyperParams::load(&path);
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(
+                serde_json::to_value(loaded.unwrap()).unwrap(),
+                serde_json::to_value(&hyper_params).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_formats() {
+        let path = path("unknown.toml");
+
+        hyper_params().save(&path).unwrap();
+        fs::write(&path, format!("unknown_field = 1\n{}", fs::read_to_string(&path).unwrap())).unwrap();
+
+        let loaded = HyperParams::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(ConfigError::Parse(_))));
+        assert!(matches!(HyperParams::load("config.yaml"), Err(ConfigError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        HyperParams::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml")).unwrap();
+    }
+}
@@ -1,5 +1,12 @@
 pub mod state;
 pub mod optimizations;
+pub mod search;
+pub mod population;
+pub mod checkpoint;
+pub mod model;
+pub mod config;
+pub mod prediction;
+pub mod observer;
 mod forward;
 mod backward;
 mod train;
@@ -0,0 +1,502 @@
+use super::{
+    optimizations::{
+        activations::{ Activation, ActivationType },
+        regularization::{ Regularization, ElasticNetRegularization, ElasticNetRegularizer, Dropout, MaxNormConstraint },
+        learning_rate::{ LearningRate, SchedulerType, Interval },
+        optimizer::{ OptimizerType, Parameters },
+        early_stopping::{ EarlyStopping, Criterion },
+        gradient_clipping::GradientClipping,
+        weight_averaging::WeightAveraging
+    },
+    state::{ Network, HyperParams, TrainingMode },
+    config::ConfigError,
+    prediction,
+    utils
+};
+use crate::dataset::Normalization;
+
+use std::{ fmt, fs, io };
+
+// Layout, all numbers little endian:
+// magic (4 bytes), version (u32),
+// number of layers (u32), neurons per layer (u32 each),
+// activation of each hidden and output layer (u8 each),
+// normalization min and max (f64 each),
+// number of parameters (u64), weights followed by biases (f64 bits each),
+// FNV-1a checksum of all previous bytes (u64)
+const MAGIC: &[u8; 4] = b"DRNN";
+
+/// Errors of saving and loading model files.
+#[derive(Debug)]
+pub enum ModelError {
+    /// The model file could not be read or written
+    Io(io::Error),
+    /// The file does not start with the magic bytes of a model file
+    InvalidMagic,
+    /// Version of the file format found in the file
+    UnsupportedVersion(u32),
+    /// The content does not match the stored checksum
+    ChecksumMismatch,
+    /// The file is shorter than its header announces
+    UnexpectedEnd,
+    /// Stored id of an unknown activation function
+    InvalidActivation(u8),
+    /// Layers, activations and parameters do not fit together
+    InvalidComposition,
+    /// The file continues after the parameters
+    TrailingBytes,
+    /// The model does not fit the hyperparameters it is loaded with
+    HyperParamsMismatch,
+    /// The hyperparameters the model is loaded with are invalid
+    InvalidHyperParams(ConfigError)
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "model file: {error}"),
+            Self::InvalidMagic => write!(f, "not a model file"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported model version {version}, expected {}", Model::VERSION),
+            Self::ChecksumMismatch => write!(f, "checksum mismatch, the model file is corrupted"),
+            Self::UnexpectedEnd => write!(f, "model file ends unexpectedly"),
+            Self::InvalidActivation(id) => write!(f, "unknown activation function {id}"),
+            Self::InvalidComposition => write!(f, "composition does not match the number of activations or parameters"),
+            Self::TrailingBytes => write!(f, "unexpected bytes after the parameters"),
+            Self::HyperParamsMismatch => write!(f, "composition or activations differ from the hyperparameters"),
+            Self::InvalidHyperParams(error) => write!(f, "{error}")
+        }
+    }
+}
+
+impl From<io::Error> for ModelError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ConfigError> for ModelError {
+    fn from(error: ConfigError) -> Self {
+        Self::InvalidHyperParams(error)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+fn activation_id(kind: ActivationType) -> u8 {
+    use ActivationType::*;
+
+    match kind {
+        LeakyRelu => 0,
+        Elu => 1,
+        Gelu => 2,
+        Sigmoid => 3,
+        Swish => 4,
+        Tanh => 5
+    }
+}
+
+fn activation_kind(id: u8) -> Result<ActivationType, ModelError> {
+    use ActivationType::*;
+
+    match id {
+        0 => Ok(LeakyRelu),
+        1 => Ok(Elu),
+        2 => Ok(Gelu),
+        3 => Ok(Sigmoid),
+        4 => Ok(Swish),
+        5 => Ok(Tanh),
+        _ => Err(ModelError::InvalidActivation(id))
+    }
+}
+
+// Sequential reads from the bytes of a model file
+// Number of weights and biases of the composition, 'None' if it overflows
+fn parameter_count(composition: &[usize]) -> Option<usize> {
+    composition.windows(2).try_fold(0usize, |count, layers| {
+        layers[0].checked_add(1)
+            .and_then(|inputs| inputs.checked_mul(layers[1]))
+            .and_then(|parameters| count.checked_add(parameters))
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ModelError> {
+        let (bytes, rest) = self.bytes.split_first_chunk::<N>().ok_or(ModelError::UnexpectedEnd)?;
+        self.bytes = rest;
+
+        Ok(*bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ModelError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ModelError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ModelError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn read_f64(&mut self) -> Result<f64, ModelError> {
+        Ok(f64::from_bits(self.read_u64()?))
+    }
+}
+
+impl HyperParams {
+    // Hyperparameters of a network that is only used for predictions, every training feature is disabled
+    pub(crate) fn inference(composition: Vec<usize>, activations: &[ActivationType], normalization: Normalization) -> Self {
+        let no_regularization = ElasticNetRegularizer { l1: 0.0, l2: 0.0 };
+
+        Self {
+            activations: Activation::get(activations),
+            regularization: Regularization {
+                elastic_net: ElasticNetRegularization {
+                    weights: no_regularization.clone(),
+                    biases: no_regularization
+                },
+                dropout_rate: Dropout { input_layer: 0.0, hidden_layer: 0.0 },
+                max_norm_constraint: MaxNormConstraint { limit: f64::INFINITY, layer_limits: None }
+            },
+            learning_rate: LearningRate {
+                alpha: 0.0,
+                scheduler: SchedulerType::Constant,
+                interval: Interval::Epoch,
+                warmup: None
+            },
+            optimizer: OptimizerType::Sgd,
+            lookahead: None,
+            gradient_clipping: GradientClipping { value: None, norm: None },
+            batch_size: 1,
+            early_stopping: EarlyStopping {
+                criterion: Criterion::Stability { stability_threshold: 0.0, patience: 1 },
+                max_epochs: None
+            },
+            weight_averaging: WeightAveraging { ema: None, swa: None, apply: None },
+            training_mode: TrainingMode::MiniBatch,
+            checkpoint: None,
+            normalization,
+            seed: None,
+            composition
+        }
+    }
+}
+
+/// Immutable trained network that only predicts, built from a trained [`Network`] or a model file.
+///
+/// Unlike [`Network::predict`] it needs no training state and predicts through '&self', so one model
+/// can be shared across threads.
+#[derive(Clone)]
+pub struct Model {
+    composition: Vec<usize>,
+    activations: Vec<Activation>,
+    normalization: Normalization,
+    parameters: Parameters
+}
+
+// Shared across the threads of a thread pool
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Model>();
+};
+
+impl From<&Network> for Model {
+    fn from(network: &Network) -> Self {
+        let HyperParams { composition, activations, normalization, .. } = &network.hyper_params;
+
+        Self {
+            composition: composition.clone(),
+            activations: activations.clone(),
+            normalization: *normalization,
+            parameters: Parameters { weights: network.weights.clone(), biases: network.biases.clone() }
+        }
+    }
+}
+
+impl From<Model> for Network {
+    // The returned network is only meant for predictions
+    fn from(model: Model) -> Self {
+        let activations = model.activations.iter().map(|activation| activation.kind).collect::<Vec<_>>();
+
+        let mut network = Self::new(HyperParams::inference(model.composition, &activations, model.normalization))
+            .expect("ERROR: invalid inference hyperparameters");
+
+        network.weights = model.parameters.weights;
+        network.biases = model.parameters.biases;
+
+        network
+    }
+}
+
+impl Model {
+    /// Version of the model file format, files of other versions are rejected by [`Model::load`].
+    pub const VERSION: u32 = 1;
+
+    /// Number of neurons in each layer.
+    pub fn composition(&self) -> &[usize] {
+        &self.composition
+    }
+
+    /// Activation function of each hidden and output layer.
+    pub fn activations(&self) -> Vec<ActivationType> {
+        self.activations.iter().map(|activation| activation.kind).collect()
+    }
+
+    /// Scaling of the raw pixel values the model was trained with.
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Number of weights and biases.
+    pub fn parameter_count(&self) -> usize {
+        self.parameters.iter().count()
+    }
+
+    /// Class probabilities of raw pixel values, e.g. grayscale values from 0 to 255 for a model trained
+    /// on MNIST. The values are scaled with the normalization saved with the model.
+    ///
+    /// Panics if the number of pixels does not match the input layer.
+    pub fn predict(&self, pixels: &[f64]) -> Vec<f64> {
+        assert_eq!(pixels.len(), self.composition[0], "ERROR: number of pixels does not match the input layer");
+
+        let inputs = pixels.iter().map(|&value| self.normalization.apply(value)).collect::<Vec<f64>>();
+
+        prediction::probabilities(&self.forward(inputs))
+    }
+
+    // Same as 'Network::forward' without dropout, returns the outputs of the output layer
+    fn forward(&self, inputs: Vec<f64>) -> Vec<f64> {
+        let Parameters { weights, biases } = &self.parameters;
+
+        self.activations.iter()
+            .zip(weights.iter().zip(biases.iter()))
+            .fold(inputs, |inputs, (activation, (weights, biases))| {
+                weights.iter()
+                    .zip(biases.iter())
+                    .map(|(weights, bias)| (activation.function)(Network::dot_product(&inputs, weights) + bias))
+                    .collect()
+            })
+    }
+
+    /// Writes the composition, activations, normalization and parameters into a binary model file.
+    pub fn save(&self, path: &str) -> Result<(), ModelError> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.composition.len() as u32).to_le_bytes());
+
+        for &neurons in &self.composition {
+            bytes.extend_from_slice(&(neurons as u32).to_le_bytes());
+        }
+
+        for activation in &self.activations {
+            bytes.push(activation_id(activation.kind));
+        }
+
+        bytes.extend_from_slice(&self.normalization.min.to_bits().to_le_bytes());
+        bytes.extend_from_slice(&self.normalization.max.to_bits().to_le_bytes());
+
+        bytes.extend_from_slice(&(self.parameter_count() as u64).to_le_bytes());
+
+        for parameter in self.parameters.iter() {
+            bytes.extend_from_slice(&parameter.to_bits().to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+
+        // Write into a temporary file first, so an interrupted write keeps the previous model intact
+        let temporary_path = format!("{path}.tmp");
+
+        fs::write(&temporary_path, bytes)?;
+        fs::rename(&temporary_path, path)?;
+
+        Ok(())
+    }
+
+    /// Reads a model file written by [`Model::save`] or [`Network::save`].
+    pub fn load(path: &str) -> Result<Self, ModelError> {
+        let bytes = fs::read(path)?;
+
+        if !bytes.starts_with(MAGIC) {
+            return Err(ModelError::InvalidMagic);
+        }
+
+        let (content, stored_checksum) = bytes.split_last_chunk::<8>().ok_or(ModelError::UnexpectedEnd)?;
+
+        let mut reader = Reader { bytes: content.get(MAGIC.len()..).ok_or(ModelError::UnexpectedEnd)? };
+
+        let version = reader.read_u32()?;
+
+        if version != Self::VERSION {
+            return Err(ModelError::UnsupportedVersion(version));
+        }
+
+        if checksum(content) != u64::from_le_bytes(*stored_checksum) {
+            return Err(ModelError::ChecksumMismatch);
+        }
+
+        let layers = reader.read_u32()? as usize;
+
+        // Each layer takes four bytes, checked before the header value decides the size of an allocation
+        if layers < 2 || layers > reader.bytes.len() / 4 {
+            return Err(ModelError::InvalidComposition);
+        }
+
+        let composition = (0..layers)
+            .map(|_| reader.read_u32().map(|neurons| neurons as usize))
+            .collect::<Result<Vec<usize>, ModelError>>()?;
+
+        if composition.contains(&0) {
+            return Err(ModelError::InvalidComposition);
+        }
+
+        let activations = (1..layers)
+            .map(|_| reader.read_u8().and_then(activation_kind))
+            .collect::<Result<Vec<ActivationType>, ModelError>>()?;
+
+        let normalization = Normalization {
+            min: reader.read_f64()?,
+            max: reader.read_f64()?
+        };
+
+        let count = parameter_count(&composition).ok_or(ModelError::InvalidComposition)?;
+
+        if reader.read_u64()? != count as u64 {
+            return Err(ModelError::InvalidComposition);
+        }
+
+        // Only allocate the parameters once the file is known to contain them
+        if reader.bytes.len() / 8 < count {
+            return Err(ModelError::UnexpectedEnd);
+        }
+
+        let mut parameters = Parameters::new(&composition);
+
+        for parameter in parameters.iter_mut() {
+            *parameter = reader.read_f64()?;
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(ModelError::TrailingBytes);
+        }
+
+        Ok(Self {
+            composition,
+            activations: Activation::get(&activations),
+            normalization,
+            parameters
+        })
+    }
+}
+
+impl Network {
+    /// Number of weights and biases.
+    pub fn parameter_count(&self) -> usize {
+        utils::flatten(&self.weights, &self.biases).count()
+    }
+
+    /// Writes the composition, activations, normalization and parameters into a binary model file.
+    pub fn save(&self, path: &str) -> Result<(), ModelError> {
+        Model::from(self).save(path)
+    }
+
+    /// Rebuilds the network from the model file alone, the returned network is only meant for predictions.
+    pub fn load(path: &str) -> Result<Self, ModelError> {
+        Model::load(path).map(Self::from)
+    }
+
+    /// Loads the parameters into a network with training hyperparameters, e.g. to continue training.
+    pub fn load_with(hyper_params: HyperParams, path: &str) -> Result<Self, ModelError> {
+        let model = Self::load(path)?;
+
+        let matches = model.hyper_params.composition == hyper_params.composition
+            && model.hyper_params.activations.iter()
+                .zip(hyper_params.activations.iter())
+                .all(|(a, b)| a.kind == b.kind);
+
+        if !matches {
+            return Err(ModelError::HyperParamsMismatch);
+        }
+
+        let mut network = Self::new(hyper_params)?;
+
+        network.weights = model.weights;
+        network.biases = model.biases;
+
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::optimizations::activations::ActivationType::*;
+
+    // Widening hidden layer, so every neuron of every layer has to be computed
+    fn network() -> Network {
+        let mut hyper_params = HyperParams::inference(vec![4, 7, 5, 3], &[Tanh, LeakyRelu, Sigmoid], Normalization::GRAYSCALE);
+        hyper_params.seed = Some(3);
+
+        Network::new(hyper_params).unwrap()
+    }
+
+    fn path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("digit-recognition-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    const PIXELS: [f64; 4] = [0.0, 64.0, 191.0, 255.0];
+
+    #[test]
+    fn model_predicts_like_network() {
+        let mut network = network();
+        let model = Model::from(&network);
+
+        assert_eq!(network.predict(&PIXELS).probabilities, model.predict(&PIXELS));
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = path("round-trip.bin");
+        let mut network = network();
+
+        network.save(&path).unwrap();
+        let model = Model::load(&path);
+        let loaded = Network::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let expected = network.predict(&PIXELS).probabilities;
+
+        assert_eq!(model.unwrap().predict(&PIXELS), expected);
+        assert_eq!(loaded.unwrap().predict(&PIXELS).probabilities, expected);
+    }
+
+    #[test]
+    fn rejects_corrupted_file() {
+        let path = path("corrupted.bin");
+
+        network().save(&path).unwrap();
+
+        // Flip a byte of the parameters
+        let mut bytes = fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        let result = Model::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ModelError::ChecksumMismatch)));
+    }
+}
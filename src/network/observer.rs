@@ -0,0 +1,73 @@
+use super::{
+    optimizations::lbfgs::LbfgsStop,
+    search::Trial,
+    population::Member,
+    checkpoint::CheckpointError
+};
+
+use std::time::Duration;
+
+/// Results of one epoch of mini-batch training.
+#[derive(Clone, Copy, Debug)]
+pub struct EpochReport {
+    /// Number of the epoch, starting at 1
+    pub epoch: u32,
+    /// Learning rate at the end of the epoch
+    pub learning_rate: f64,
+    /// Validation accuracy
+    pub accuracy: f64,
+    /// Validation cost including the regularization penalty
+    pub cost: f64,
+    /// Regularization penalty of the parameters
+    pub regularization_penalty: f64,
+    /// Steps with clipped gradients, 'None' if gradient clipping is disabled
+    pub clipped: Option<u32>,
+    /// Training time of all epochs so far, without the validation
+    pub duration: Duration,
+    /// Set for the last epoch, once the early stopping criterion is met
+    pub stopped: bool
+}
+
+/// Results of one iteration of full-batch L-BFGS training.
+#[derive(Clone, Copy, Debug)]
+pub struct LbfgsReport {
+    /// Number of the iteration, starting at 1
+    pub iteration: u32,
+    /// Step size accepted by the line search
+    pub step_size: f64,
+    /// Validation accuracy
+    pub accuracy: f64,
+    /// Validation cost including the regularization penalty
+    pub cost: f64,
+    /// Regularization penalty of the parameters
+    pub regularization_penalty: f64,
+    /// Training time of all iterations so far, without the validation
+    pub duration: Duration,
+    /// Set for the last iteration
+    pub stop: Option<LbfgsStop>
+}
+
+/// Receives the progress of training, hyperparameter searches and population based training, e.g. to
+/// print it. The library never prints, every method does nothing unless it is implemented.
+pub trait Observer {
+    /// Called after every epoch of [`Network::train`](crate::Network::train) with mini-batches.
+    fn epoch(&mut self, _report: &EpochReport) {}
+
+    /// Called after every iteration of [`Network::train`](crate::Network::train) with L-BFGS.
+    fn lbfgs_iteration(&mut self, _report: &LbfgsReport) {}
+
+    /// Called once a trial of a [`Search`](crate::Search) is trained for its epochs or stopped early.
+    fn trial(&mut self, _trial: &Trial) {}
+
+    /// Called after every interval of [`PopulationBasedTraining`](crate::PopulationBasedTraining). The members
+    /// are ranked from best to worst and the last 'replaced' ones were just replaced by perturbed copies.
+    fn population(&mut self, _epoch: u32, _members: &[Member], _replaced: usize) {}
+
+    /// Called if a checkpoint could not be written, training continues and the next interval tries again.
+    fn checkpoint_failed(&mut self, _path: &str, _error: &CheckpointError) {}
+}
+
+/// Observer that ignores all progress.
+pub struct Silent;
+
+impl Observer for Silent {}
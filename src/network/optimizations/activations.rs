@@ -1,19 +1,31 @@
-#![allow(dead_code)]
+use serde::{ Serialize, Deserialize };
 
+/// Activation function of a layer.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ActivationType {
+    /// Rectified linear unit with a small slope for negative inputs
     LeakyRelu,
+    /// Exponential linear unit
     Elu,
+    /// Gaussian error linear unit
     Gelu,
+    /// Logistic function
     Sigmoid,
+    /// Input times its sigmoid
     Swish,
+    /// Hyperbolic tangent
     Tanh,
 }
 
 use ActivationType::*;
 
+/// Activation function of a layer with its derivative, written to config files as its [`ActivationType`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "ActivationType", into = "ActivationType")]
 pub struct Activation {
-    pub function: fn(f64) -> f64,
-    pub derivative: fn(f64) -> f64,
+    pub(crate) kind: ActivationType,
+    pub(crate) function: fn(f64) -> f64,
+    pub(crate) derivative: fn(f64) -> f64,
 }
 
 const SQRT_2_OVER_PI: f64 = 0.797_884_560_802_865_4;
@@ -30,10 +42,12 @@ impl Activation {
         (exp_pos - exp_neg) / (exp_pos + exp_neg)
     }
 
+    /// One activation function for each type, e.g. for each hidden and output layer.
     pub fn get(activations: &[ActivationType]) -> Vec<Self> {
         activations.iter().map(|activation| {            
             match activation {
                 Sigmoid => Self {
+                    kind: Sigmoid,
                     function: Self::sigmoid,
                     derivative: |x| {
                         let sigmoid_x = Self::sigmoid(x);
@@ -42,36 +56,73 @@ impl Activation {
                     }
                 },
                 Swish => Self {
+                    kind: Swish,
                     function: |x| x * Self::sigmoid(x),
                     derivative: |x| {
                         let sigmoid_x = Self::sigmoid(x);
                     
-                        x * sigmoid_x + sigmoid_x * (1.0 - sigmoid_x)
+                        sigmoid_x + x * sigmoid_x * (1.0 - sigmoid_x)
                     }
                 },
                 Tanh => Self {
+                    kind: Tanh,
                     function: Self::tanh,
                     derivative: |x| 1.0 - Self::tanh(x).powi(2),
                 },
                 LeakyRelu => Self {
+                    kind: LeakyRelu,
                     function: |x| x.max(0.01 * x),
                     derivative: |x| match x >= 0.0 { true => 1.0, false => 0.01 }
                 },
                 Elu => Self {
+                    kind: Elu,
                     function: |x| match x >= 0.0 { true => x, false => 1.0 * x.exp_m1() },
                     derivative: |x| match x >= 0.0 { true => 1.0, false => 1.0 * x.exp_m1() + 1.0 } 
                 },
-                Gelu => Self {               
+                Gelu => Self {
+                    kind: Gelu,
                     function: |x| 0.5 * x * (1.0 + Self::tanh(SQRT_2_OVER_PI * (0.044_715 * x.powi(3) + x))),
                     derivative: |x| {
-                        let sub_calculation = SQRT_2_OVER_PI * (0.044_715 * x.powi(3) + x); 
+                        let tanh_x = Self::tanh(SQRT_2_OVER_PI * (0.044_715 * x.powi(3) + x));
 
-                        0.5 * (1.0 + Self::tanh(sub_calculation) + 0.5 * x * (1.0 / x.cosh()).powi(2))
-                        * sub_calculation
-                        * SQRT_2_OVER_PI * (1.0 + 3.0 * 0.044_715_f64 * x.powi(2))
+                        0.5 * (1.0 + tanh_x) +
+                        0.5 * x * (1.0 - tanh_x.powi(2)) * SQRT_2_OVER_PI * (1.0 + 3.0 * 0.044_715 * x.powi(2))
                     }
                 }
             }
         }).collect()
     }
 }
+
+impl From<ActivationType> for Activation {
+    fn from(kind: ActivationType) -> Self {
+        Self::get(&[kind]).remove(0)
+    }
+}
+
+impl From<Activation> for ActivationType {
+    fn from(activation: Activation) -> Self {
+        activation.kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f64 = 1e-6;
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let activations = Activation::get(&[LeakyRelu, Elu, Gelu, Sigmoid, Swish, Tanh]);
+
+        for activation in activations {
+            // Away from 0, where the derivatives of Leaky ReLU and ELU jump
+            for x in [-3.0, -1.2, -0.4, 0.3, 0.8, 2.5] {
+                let numerical = ((activation.function)(x + STEP) - (activation.function)(x - STEP)) / (2.0 * STEP);
+
+                assert!(((activation.derivative)(x) - numerical).abs() < 1e-6, "{:?} at {x}", activation.kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::network::state::Vec2D;
+use super::optimizer::{ self, Optimizer, Parameters };
+
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Adaptive learning rates from the sum of all squared gradients.
+pub struct AdaGradHyperParams {
+    /// Added to the denominator for numerical stability
+    pub epsilon: f64
+}
+
+pub struct AdaGrad {
+    pub hyper_params: AdaGradHyperParams,
+    pub iteration: i32,
+    pub sum_of_squares: Parameters
+}
+
+impl AdaGrad {
+    pub fn new(hyper_params: AdaGradHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            sum_of_squares: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for AdaGrad {
+    fn compute_steps(&mut self, _: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let AdaGradHyperParams { epsilon } = self.hyper_params;
+
+        self.iteration += 1;
+
+        for (gradient, sum_of_squares) in gradients.iter_mut().zip(self.sum_of_squares.iter_mut()) {
+            *sum_of_squares += gradient.powi(2);
+            *gradient = alpha * *gradient / (sum_of_squares.sqrt() + epsilon);
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.sum_of_squares.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.sum_of_squares.read(state);
+    }
+}
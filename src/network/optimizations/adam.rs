@@ -1,54 +1,124 @@
-use crate::network::{ state::{Vec2D, Network}, utils};
+use crate::network::state::Vec2D;
+use super::optimizer::{ self, Optimizer, Parameters };
 
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Adaptive moment estimation.
 pub struct AdamHyperParams {
+    /// Decay of the moving average of the gradients
     pub beta_1: f64,
+    /// Decay of the moving average of the squared gradients
     pub beta_2: f64,
-    pub epsilon: f64
+    /// Added to the denominator for numerical stability
+    pub epsilon: f64,
+    /// Use the running maximum of the second moment (AMSGrad)
+    pub amsgrad: bool
+}
+
+/// Adam with decoupled weight decay.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdamWHyperParams {
+    /// Hyperparameters of the Adam update
+    pub adam: AdamHyperParams,
+    /// Decoupled weight decay, applied directly to the weights instead of the gradients
+    pub weight_decay: f64
 }
 
-#[derive(Clone)]
-pub struct Moment {
-    pub weights: Vec<Vec2D>,
-    pub biases: Vec2D
+pub struct Adam {
+    pub hyper_params: AdamHyperParams,
+    pub weight_decay: f64,
+    pub iteration: i32,
+    pub moment_1: Parameters,
+    pub moment_2: Parameters,
+    pub max_moment_2: Option<Parameters>
 }
 
-impl Moment {
-    pub fn new(composition: &[usize]) -> Self {
+impl Adam {
+    pub fn new(hyper_params: AdamHyperParams, composition: &[usize]) -> Self {
         Self {
-            weights: utils::zeros_3d_vec(composition),
-            biases: utils::zeros_2d_vec(composition, 1)
+            hyper_params,
+            weight_decay: 0.0,
+            iteration: 0,
+            moment_1: Parameters::new(composition),
+            moment_2: Parameters::new(composition),
+            max_moment_2: match hyper_params.amsgrad {
+                true => Some(Parameters::new(composition)),
+                false => None
+            }
         }
     }
-}
 
-// 'impl Network' because 'impl Moment' significantly reduces performance during backpropagation
-impl Network {   
-    pub fn compute_moments(
-        moment_1: &mut f64, moment_2: &mut f64, optimizer: &AdamHyperParams, gradient: &f64, iteration: &i32
-    ) -> (f64, f64) {
-        *moment_1 = optimizer.beta_1 * *moment_1 + (1.0 - optimizer.beta_1) * gradient;
-        *moment_2 = optimizer.beta_2 * *moment_2 + (1.0 - optimizer.beta_2) * gradient.powi(2);
-
-        // Bias-corrected moments
-        (
-            *moment_1 / (1.0 - optimizer.beta_1.powi(*iteration)),
-            *moment_2 / (1.0 - optimizer.beta_2.powi(*iteration))
-        )
+    pub fn with_weight_decay(AdamWHyperParams { adam, weight_decay }: AdamWHyperParams, composition: &[usize]) -> Self {
+        Self {
+            weight_decay,
+            ..Self::new(adam, composition)
+        }
     }
 }
 
-pub struct Adam {
-    pub iteration: i32,
-    pub moment_1: Moment,
-    pub moment_2: Moment
-}
+impl Optimizer for Adam {
+    fn compute_steps(&mut self, weights: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let AdamHyperParams { beta_1, beta_2, epsilon, .. } = self.hyper_params;
 
-impl Adam {
-    pub fn new(composition: &[usize]) -> Self {
-        Self {
-            iteration: 0,
-            moment_1: Moment::new(composition),
-            moment_2: Moment::new(composition)
+        self.iteration += 1;
+
+        // Bias correction of the moments
+        let correction_1 = 1.0 - beta_1.powi(self.iteration);
+        let correction_2 = 1.0 - beta_2.powi(self.iteration);
+
+        let mut max_moments_2 = self.max_moment_2.as_mut().map(|moment| moment.iter_mut());
+
+        for ((gradient, moment_1), moment_2) in gradients.iter_mut()
+            .zip(self.moment_1.iter_mut())
+            .zip(self.moment_2.iter_mut())
+        {
+            *moment_1 = beta_1 * *moment_1 + (1.0 - beta_1) * *gradient;
+            *moment_2 = beta_2 * *moment_2 + (1.0 - beta_2) * gradient.powi(2);
+
+            let moment_2 = match max_moments_2.as_mut().and_then(Iterator::next) {
+                Some(max_moment_2) => {
+                    *max_moment_2 = max_moment_2.max(*moment_2);
+                    *max_moment_2
+                },
+                None => *moment_2
+            };
+
+            *gradient = alpha * (*moment_1 / correction_1) / ((moment_2 / correction_2).sqrt() + epsilon);
+        }
+
+        // AdamW: decay the weights outside of the moment estimates, biases are not decayed
+        if self.weight_decay != 0.0 {
+            for (step, weight) in gradients.weights.iter_mut().flatten().flatten()
+                .zip(weights.iter().flatten().flatten())
+            {
+                *step += alpha * self.weight_decay * weight;
+            }
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.moment_1.iter().copied())
+            .chain(self.moment_2.iter().copied())
+            .chain(self.max_moment_2.iter().flat_map(|max_moment_2| max_moment_2.iter().copied()))
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.moment_1.read(state);
+        self.moment_2.read(state);
+
+        if let Some(max_moment_2) = &mut self.max_moment_2 {
+            max_moment_2.read(state);
         }
     }
 }
@@ -1,43 +1,103 @@
-use crate::network::{ state::{ Vec2D, Network }, utils };
+use crate::network::state::Network;
+use super::optimizer::Parameters;
 
+// Sum of the raw gradients of all samples in the current batch
 pub struct Batch {
-    pub weight_updates: Vec<Vec2D>,
-    pub bias_updates: Vec2D
+    pub gradients: Parameters
 }
 
 impl Batch {
     pub fn new(composition: &[usize]) -> Self {
         Self {
-            weight_updates: utils::zeros_3d_vec(composition),
-            bias_updates: utils::zeros_2d_vec(composition, 1)
+            gradients: Parameters::new(composition)
         }
     }
-    
-    pub fn update(network: &mut Network, chunk_size: f64) {
-        for (weights, weight_updates) in network.weights.iter_mut()
-            .zip(network.batch.weight_updates.iter_mut())
-        {
-            for (weights, weight_updates) in weights.iter_mut()
-                .zip(weight_updates.iter_mut())
-            {
-                for (weight, weight_update) in weights.iter_mut()
-                    .zip(weight_updates.iter_mut())
-                {
-                    *weight = *weight_update / chunk_size;
-                    *weight_update = 0.0;
-                }
+
+    pub fn accumulate(network: &mut Network) {
+        for (sum, gradient) in network.batch.gradients.iter_mut().zip(network.gradients.iter()) {
+            *sum += gradient;
+        }
+    }
+
+    // Replace the gradients of the network with the mean gradient of the batch and reset the sum
+    pub fn average(network: &mut Network, chunk_size: f64) {
+        for (gradient, sum) in network.gradients.iter_mut().zip(network.batch.gradients.iter_mut()) {
+            *gradient = *sum / chunk_size;
+            *sum = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::{ Data, Normalization },
+        network::{ state::HyperParams, optimizations::activations::ActivationType::*, utils }
+    };
+
+    fn hyper_params(batch_size: usize) -> HyperParams {
+        let mut hyper_params = HyperParams::inference(vec![4, 5, 3], &[Tanh, Sigmoid], Normalization::GRAYSCALE);
+
+        hyper_params.learning_rate.alpha = 0.1;
+        hyper_params.batch_size = batch_size;
+        hyper_params.seed = Some(13);
+
+        hyper_params
+    }
+
+    fn data() -> Data {
+        Data {
+            inputs: (0..10).map(|sample| (0..4).map(|pixel| ((sample * 3 + pixel) % 7) as f64 / 6.0).collect()).collect(),
+            targets: (0..10).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    #[test]
+    fn one_optimizer_step_per_batch() {
+        let data = data();
+
+        // 10 samples in batches of 4, the last batch holds the remaining 2 samples
+        let mut network = Network::new(hyper_params(4)).unwrap();
+        network.train_epoch(&data, 1);
+
+        assert_eq!(network.iteration(), 3);
+    }
+
+    #[test]
+    fn step_uses_mean_gradient_of_batch() {
+        let data = data();
+
+        let mut trained = Network::new(hyper_params(data.inputs.len())).unwrap();
+        let mut expected = Network::new(hyper_params(data.inputs.len())).unwrap();
+
+        let mut sum = Parameters::new(&expected.hyper_params.composition);
+
+        for (inputs, targets) in data.inputs.iter().zip(data.targets.iter()) {
+            expected.forward(inputs);
+            expected.backward(targets);
+
+            for (sum, gradient) in sum.iter_mut().zip(expected.gradients.iter()) {
+                *sum += gradient;
             }
         }
-        
-        for (biases, bias_updates) in network.biases.iter_mut()
-            .zip(network.batch.bias_updates.iter_mut())
+
+        // Plain SGD, so the only step subtracts alpha times the mean gradient
+        for (parameter, sum) in expected.weights.iter_mut().flatten().flatten()
+            .chain(expected.biases.iter_mut().flatten())
+            .zip(sum.iter())
         {
-            for (bias, bias_update) in biases.iter_mut()
-                .zip(bias_updates.iter_mut()) 
-            {
-                *bias = *bias_update / chunk_size;
-                *bias_update = 0.0;
-            }
+            *parameter -= 0.1 * sum / data.inputs.len() as f64;
+        }
+
+        trained.train_epoch(&data, 1);
+
+        assert_eq!(trained.iteration(), 1);
+
+        for (trained, expected) in utils::flatten(&trained.weights, &trained.biases)
+            .zip(utils::flatten(&expected.weights, &expected.biases))
+        {
+            assert!((trained - expected).abs() < 1e-12, "{trained} != {expected}");
         }
     }
 }
@@ -1,17 +1,123 @@
-use crate::network::state::Network;
+use crate::{ dataset::Data, network::state::Network };
+use super::optimizer::Parameters;
 
+use serde::{ Serialize, Deserialize };
+use std::cmp::Ordering;
+
+/// Score of the network on the validation data.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Metric {
+    /// Validation cost including the regularization penalty
+    Loss,
+    /// Fraction of correct predictions
+    Accuracy,
+    /// Macro-averaged F1 score over all classes
+    F1
+}
+
+impl Metric {
+    pub(crate) fn score(&self, network: &mut Network, accuracy: f64, cost: f64, data: &Data) -> f64 {
+        match self {
+            Self::Loss => cost,
+            Self::Accuracy => accuracy,
+            Self::F1 => network.compute_f1_score(data)
+        }
+    }
+}
+
+/// Whether a lower or a higher [`Metric`] is better.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Direction {
+    /// Lower is better, e.g. for the loss
+    Minimize,
+    /// Higher is better, e.g. for the accuracy
+    Maximize
+}
+
+impl Direction {
+    // Better scores compare as less, non-finite scores, e.g. of diverged training, are the worst in both directions
+    pub(crate) fn compare(&self, a: f64, b: f64) -> Ordering {
+        match (a.is_finite(), b.is_finite()) {
+            (true, true) => match self {
+                Self::Minimize => a.total_cmp(&b),
+                Self::Maximize => b.total_cmp(&a)
+            },
+            (a_is_finite, b_is_finite) => b_is_finite.cmp(&a_is_finite)
+        }
+    }
+
+    // Whether 'score' beats 'best' by more than 'min_delta'
+    pub(crate) fn is_improvement(&self, score: f64, best: f64, min_delta: f64) -> bool {
+        match self {
+            Self::Minimize => score < best - min_delta,
+            Self::Maximize => score > best + min_delta
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Stops once the monitored metric stops improving.
+pub struct BestModel {
+    /// Monitored metric
+    pub metric: Metric,
+    /// Whether the metric is minimized or maximized
+    pub direction: Direction,
+    /// Number of epochs without improvement on the best epoch before stopping
+    pub patience: u32,
+    /// Minimum change of the metric that counts as improvement
+    pub min_delta: f64,
+    /// Replace the last parameters with the parameters of the best epoch once training ends
+    pub restore_best: bool
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// When to stop training early.
+pub enum Criterion {
+    /// If the mean difference of the network accuracy over the last
+    /// `patience` epochs falls below `stability_threshold`, stop training
+    Stability {
+        /// Mean difference of the accuracy below which training stops
+        stability_threshold: f64,
+        /// Number of recent epochs to average over
+        patience: usize
+    },
+    /// Stop once the monitored metric has not improved for `patience` epochs
+    Best(BestModel)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Stops training before the network overfits.
 pub struct EarlyStopping {
-    pub stability_threshold: f64,
-    pub patience: usize
+    /// When to stop
+    pub criterion: Criterion,
+    /// Stop after this many epochs regardless of the criterion
+    pub max_epochs: Option<u32>
+}
+
+// Snapshot of the epoch with the best monitored metric
+pub struct BestParameters {
+    pub epoch: u32,
+    pub score: f64,
+    pub parameters: Parameters
 }
 
 impl EarlyStopping {
-    pub fn check(network: &mut Network, accuracy: f64) -> bool {
-        let EarlyStopping { stability_threshold, patience } = &network.hyper_params.early_stopping;
-        
+    pub(crate) fn check(network: &mut Network, epoch: u32, accuracy: f64, cost: f64, validation_data: &Data) -> bool {
         network.performance.push(accuracy);
 
-        if network.performance.len() >= *patience {
+        let stop = match network.hyper_params.early_stopping.criterion {
+            Criterion::Stability { stability_threshold, patience } => Self::check_stability(network, stability_threshold, patience),
+            Criterion::Best(best_model) => Self::check_best(network, best_model, epoch, accuracy, cost, validation_data)
+        };
+
+        stop || network.hyper_params.early_stopping.max_epochs.is_some_and(|max_epochs| epoch >= max_epochs)
+    }
+
+    fn check_stability(network: &Network, stability_threshold: f64, patience: usize) -> bool {
+        if network.performance.len() >= patience {
             let recent_performance = &network.performance[(network.performance.len() - patience)..];
 
             let mut sum_diff = 0.0;
@@ -20,11 +126,130 @@ impl EarlyStopping {
                 sum_diff += recent_performance[index] - recent_performance[index - 1];
             }
 
-            let mean_diff = sum_diff / *patience as f64;
+            let mean_diff = sum_diff / patience as f64;
 
-            return mean_diff <= *stability_threshold
+            return mean_diff <= stability_threshold
         }
 
         false
     }
+
+    fn check_best(
+        network: &mut Network,
+        best_model: BestModel,
+        epoch: u32,
+        accuracy: f64,
+        cost: f64,
+        validation_data: &Data
+    ) -> bool {
+        let BestModel { metric, direction, patience, min_delta, .. } = best_model;
+
+        let score = metric.score(network, accuracy, cost, validation_data);
+
+        let improved = network.best.as_ref()
+            .is_none_or(|best| direction.is_improvement(score, best.score, min_delta));
+
+        if improved {
+            network.best = Some(BestParameters {
+                epoch,
+                score,
+                parameters: Parameters {
+                    weights: network.weights.clone(),
+                    biases: network.biases.clone()
+                }
+            });
+        }
+
+        network.best.as_ref().is_some_and(|best| epoch - best.epoch >= patience)
+    }
+
+    // Called once training ends, returns whether the parameters were replaced
+    pub(crate) fn restore_best(network: &mut Network) -> bool {
+        let Criterion::Best(BestModel { restore_best: true, .. }) = network.hyper_params.early_stopping.criterion else {
+            return false;
+        };
+
+        match &network.best {
+            Some(best) => {
+                network.weights = best.parameters.weights.clone();
+                network.biases = best.parameters.biases.clone();
+
+                true
+            },
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Normalization,
+        network::{ state::HyperParams, observer::Silent, optimizations::activations::ActivationType::* }
+    };
+
+    fn best_model(restore_best: bool) -> BestModel {
+        BestModel { metric: Metric::Loss, direction: Direction::Minimize, patience: 2, min_delta: 0.0, restore_best }
+    }
+
+    fn network(best_model: BestModel) -> Network {
+        let mut hyper_params = HyperParams::inference(vec![4, 6, 3], &[Tanh, Sigmoid], Normalization::GRAYSCALE);
+
+        hyper_params.early_stopping = EarlyStopping { criterion: Criterion::Best(best_model), max_epochs: Some(20) };
+        hyper_params.learning_rate.alpha = 5.0;
+        hyper_params.seed = Some(19);
+
+        Network::new(hyper_params).unwrap()
+    }
+
+    fn data() -> Data {
+        Data {
+            inputs: (0..9).map(|sample| (0..4).map(|pixel| ((sample * 2 + pixel) % 5) as f64 / 4.0).collect()).collect(),
+            targets: (0..9).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    #[test]
+    fn stops_after_patience_and_restores_best_epoch() {
+        let data = data();
+
+        for restore_best in [true, false] {
+            let mut network = network(best_model(restore_best));
+
+            // The weights of each epoch are marked with the epoch, the second epoch has the lowest cost
+            for (epoch, cost) in [(1, 1.0), (2, 0.5), (3, 0.8)] {
+                network.weights[0][0][0] = epoch as f64;
+                assert!(!EarlyStopping::check(&mut network, epoch, 0.0, cost, &data));
+            }
+
+            network.weights[0][0][0] = 4.0;
+            assert!(EarlyStopping::check(&mut network, 4, 0.0, 0.9, &data));
+
+            assert_eq!(network.best_epoch(), Some((2, 0.5)));
+            assert_eq!(EarlyStopping::restore_best(&mut network), restore_best);
+
+            let expected = match restore_best {
+                true => 2.0,
+                false => 4.0
+            };
+
+            assert_eq!(network.weights[0][0][0], expected);
+        }
+    }
+
+    #[test]
+    fn training_ends_with_parameters_of_best_epoch() {
+        let data = data();
+        let mut network = network(best_model(true));
+
+        // The large learning rate overshoots, so the cost rises again after the best epoch
+        network.train(&data, &data, &mut Silent);
+
+        let (_, cost) = network.test(&data);
+        let (epoch, score) = network.best_epoch().unwrap();
+
+        assert!(epoch < network.epoch);
+        assert_eq!(cost, score);
+    }
 }
@@ -0,0 +1,49 @@
+use crate::network::state::Network;
+
+use serde::{ Serialize, Deserialize };
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Limits the gradients before each optimizer step, 'None' disables a limit.
+pub struct GradientClipping {
+    /// Clamp every gradient to [-value, value]
+    pub value: Option<f64>,
+    /// Rescale all gradients if their global L2 norm across all layers exceeds this value
+    pub norm: Option<f64>
+}
+
+impl GradientClipping {
+    /// Whether any limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.value.is_some() || self.norm.is_some()
+    }
+
+    // Clips the gradients before the optimizer step, returns whether any gradient was clipped
+    pub(crate) fn clip(network: &mut Network) -> bool {
+        let GradientClipping { value, norm } = network.hyper_params.gradient_clipping;
+        let mut clipped = false;
+
+        if let Some(value) = value {
+            for gradient in network.gradients.iter_mut() {
+                if gradient.abs() > value {
+                    *gradient = gradient.clamp(-value, value);
+                    clipped = true;
+                }
+            }
+        }
+
+        if let Some(norm) = norm {
+            let global_l2_norm = network.gradients.iter().map(|gradient| gradient.powi(2)).sum::<f64>().sqrt();
+
+            if global_l2_norm > norm {
+                for gradient in network.gradients.iter_mut() {
+                    *gradient *= norm / global_l2_norm;
+                }
+
+                clipped = true;
+            }
+        }
+
+        clipped
+    }
+}
@@ -0,0 +1,95 @@
+use crate::network::state::Vec2D;
+use super::{ optimizer::{ self, Optimizer, Parameters }, regularization::Regularization };
+
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Adam with layer-wise trust ratios for large batches.
+pub struct LambHyperParams {
+    /// Decay of the moving average of the gradients
+    pub beta_1: f64,
+    /// Decay of the moving average of the squared gradients
+    pub beta_2: f64,
+    /// Added to the denominator for numerical stability
+    pub epsilon: f64,
+    /// Weight decay added to the update
+    pub weight_decay: f64
+}
+
+pub struct Lamb {
+    pub hyper_params: LambHyperParams,
+    pub iteration: i32,
+    pub moment_1: Parameters,
+    pub moment_2: Parameters
+}
+
+impl Lamb {
+    pub fn new(hyper_params: LambHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            moment_1: Parameters::new(composition),
+            moment_2: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for Lamb {
+    fn compute_steps(&mut self, weights: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let LambHyperParams { beta_1, beta_2, epsilon, weight_decay } = self.hyper_params;
+
+        self.iteration += 1;
+
+        let correction_1 = 1.0 - beta_1.powi(self.iteration);
+        let correction_2 = 1.0 - beta_2.powi(self.iteration);
+
+        for ((gradient, moment_1), moment_2) in gradients.iter_mut()
+            .zip(self.moment_1.iter_mut())
+            .zip(self.moment_2.iter_mut())
+        {
+            *moment_1 = beta_1 * *moment_1 + (1.0 - beta_1) * *gradient;
+            *moment_2 = beta_2 * *moment_2 + (1.0 - beta_2) * gradient.powi(2);
+
+            *gradient = (*moment_1 / correction_1) / ((*moment_2 / correction_2).sqrt() + epsilon);
+        }
+
+        // Scale the Adam direction of every layer by the ratio of its weight norm to its update norm
+        for (steps, weights) in gradients.weights.iter_mut().zip(weights) {
+            for (step, weight) in steps.iter_mut().flatten().zip(weights.iter().flatten()) {
+                *step += weight_decay * weight;
+            }
+
+            let trust_ratio = optimizer::trust_ratio(
+                Regularization::compute_layer_l2_norm(weights),
+                Regularization::compute_layer_l2_norm(steps)
+            );
+
+            for step in steps.iter_mut().flatten() {
+                *step *= alpha * trust_ratio;
+            }
+        }
+
+        for step in gradients.biases.iter_mut().flatten() {
+            *step *= alpha;
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.moment_1.iter().copied())
+            .chain(self.moment_2.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.moment_1.read(state);
+        self.moment_2.read(state);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::network::state::Vec2D;
+use super::{ optimizer::{ self, Optimizer, Parameters }, regularization::Regularization };
+
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Momentum with layer-wise trust ratios for large batches.
+pub struct LarsHyperParams {
+    /// Decay of the velocity
+    pub momentum: f64,
+    /// Weight decay added to the gradients
+    pub weight_decay: f64,
+    /// Scales the layer-wise trust ratio
+    pub trust_coefficient: f64
+}
+
+pub struct Lars {
+    pub hyper_params: LarsHyperParams,
+    pub iteration: i32,
+    pub velocity: Parameters
+}
+
+impl Lars {
+    pub fn new(hyper_params: LarsHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            velocity: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for Lars {
+    fn compute_steps(&mut self, weights: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let LarsHyperParams { momentum, weight_decay, trust_coefficient } = self.hyper_params;
+
+        self.iteration += 1;
+
+        for ((gradients, velocities), weights) in gradients.weights.iter_mut()
+            .zip(self.velocity.weights.iter_mut())
+            .zip(weights)
+        {
+            let weight_norm = Regularization::compute_layer_l2_norm(weights);
+            let update_norm = Regularization::compute_layer_l2_norm(gradients) + weight_decay * weight_norm;
+
+            let local_alpha = alpha * trust_coefficient * optimizer::trust_ratio(weight_norm, update_norm);
+
+            for ((gradient, velocity), weight) in gradients.iter_mut().flatten()
+                .zip(velocities.iter_mut().flatten())
+                .zip(weights.iter().flatten())
+            {
+                *velocity = momentum * *velocity + local_alpha * (*gradient + weight_decay * weight);
+                *gradient = *velocity;
+            }
+        }
+
+        // Biases are updated with plain momentum
+        for (gradient, velocity) in gradients.biases.iter_mut().flatten()
+            .zip(self.velocity.biases.iter_mut().flatten())
+        {
+            *velocity = momentum * *velocity + alpha * *gradient;
+            *gradient = *velocity;
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.velocity.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.velocity.read(state);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{ dataset::Data, network::{ state::Network, observer::{ Observer, LbfgsReport }, utils } };
+use super::regularization::Dropout;
+
+use serde::{ Serialize, Deserialize };
+use std::{ collections::VecDeque, time::{ Instant, Duration } };
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Full-batch limited-memory BFGS.
+pub struct LbfgsHyperParams {
+    /// Number of most recent curvature pairs used to approximate the inverse Hessian
+    pub history_size: usize,
+    /// Iterations over the full training data at most
+    pub max_iterations: u32,
+    /// Stop once the L2 norm of the gradient falls below this value
+    pub tolerance: f64
+}
+
+/// Why full-batch training stopped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LbfgsStop {
+    /// The L2 norm of the gradient fell below the tolerance
+    Converged,
+    /// Not even a steepest descent step decreased the cost
+    LineSearchFailed,
+    /// 'max_iterations' were trained
+    MaxIterations
+}
+
+// Constant of the Armijo condition used by the backtracking line search
+const SUFFICIENT_DECREASE: f64 = 1e-4;
+const MAX_LINE_SEARCH_STEPS: u32 = 30;
+
+// Difference of the parameters ('s') and of the gradients ('y') between two iterations
+struct CurvaturePair {
+    s: Vec<f64>,
+    y: Vec<f64>,
+    rho: f64
+}
+
+impl Network {
+    fn parameters(&self) -> Vec<f64> {
+        utils::flatten(&self.weights, &self.biases).copied().collect()
+    }
+
+    fn set_parameters(&mut self, parameters: &[f64]) {
+        for (parameter, value) in self.weights.iter_mut().flatten().flatten()
+            .chain(self.biases.iter_mut().flatten())
+            .zip(parameters)
+        {
+            *parameter = *value;
+        }
+    }
+
+    // Average cost and gradient over the whole data set, both including the regularization penalty
+    fn compute_full_batch_gradient(&mut self, data: &Data) -> (f64, Vec<f64>) {
+        let mut cost = 0.0;
+        let mut gradient = vec![0.0; self.gradients.iter().count()];
+
+        for (inputs, targets) in data.inputs.iter().zip(data.targets.iter()) {
+            self.forward(inputs);
+            self.backward(targets);
+
+            cost += Self::compute_cost(self.outputs.last().unwrap(), targets);
+
+            for (sum, gradient) in gradient.iter_mut().zip(self.gradients.iter()) {
+                *sum += gradient;
+            }
+        }
+
+        let samples = data.inputs.len() as f64;
+
+        for sum in gradient.iter_mut() {
+            *sum /= samples;
+        }
+
+        (cost / samples + self.compute_regularization_penalty(), gradient)
+    }
+
+    // Two-loop recursion, returns the descent direction '-H * gradient'
+    fn compute_direction(gradient: &[f64], history: &VecDeque<CurvaturePair>) -> Vec<f64> {
+        let mut direction = gradient.to_vec();
+        let mut alphas = Vec::with_capacity(history.len());
+
+        for CurvaturePair { s, y, rho } in history.iter().rev() {
+            let alpha = rho * Self::dot_product(s, &direction);
+
+            for (direction, y) in direction.iter_mut().zip(y) {
+                *direction -= alpha * y;
+            }
+
+            alphas.push(alpha);
+        }
+
+        // Scale by the curvature of the most recent pair as initial inverse Hessian
+        if let Some(CurvaturePair { s, y, .. }) = history.back() {
+            let gamma = Self::dot_product(s, y) / Self::dot_product(y, y);
+
+            for direction in direction.iter_mut() {
+                *direction *= gamma;
+            }
+        }
+
+        for (CurvaturePair { s, y, rho }, alpha) in history.iter().zip(alphas.iter().rev()) {
+            let beta = rho * Self::dot_product(y, &direction);
+
+            for (direction, s) in direction.iter_mut().zip(s) {
+                *direction += (alpha - beta) * s;
+            }
+        }
+
+        direction.iter().map(|direction| -direction).collect()
+    }
+
+    pub(crate) fn train_lbfgs(
+        &mut self,
+        train_data: &Data,
+        validation_data: &Data,
+        hyper_params: LbfgsHyperParams,
+        observer: &mut dyn Observer
+    ) {
+        let LbfgsHyperParams { history_size, max_iterations, tolerance } = hyper_params;
+
+        let mut duration = Duration::ZERO;
+        let mut history = VecDeque::with_capacity(history_size);
+
+        // Full-batch training is deterministic, no neurons are dropped out
+        Dropout::set_all_active_mask(self);
+
+        let mut parameters = self.parameters();
+        let (mut cost, mut gradient) = self.compute_full_batch_gradient(train_data);
+
+        for iteration in 1..=max_iterations {
+            let timestamp = Instant::now();
+
+            let mut direction = Self::compute_direction(&gradient, &history);
+            let mut slope = Self::dot_product(&gradient, &direction);
+
+            // Fall back to steepest descent if the approximation does not point downhill
+            if slope >= 0.0 {
+                history.clear();
+                direction = gradient.iter().map(|gradient| -gradient).collect();
+                slope = Self::dot_product(&gradient, &direction);
+            }
+
+            let mut step_size = match history.is_empty() {
+                true => (1.0 / Self::dot_product(&gradient, &gradient).sqrt()).min(1.0),
+                false => 1.0
+            };
+
+            let mut line_search_steps = 0;
+
+            let (candidate, candidate_cost, candidate_gradient) = loop {
+                let candidate: Vec<f64> = parameters.iter().zip(&direction)
+                    .map(|(parameter, direction)| parameter + step_size * direction)
+                    .collect();
+
+                self.set_parameters(&candidate);
+                let (candidate_cost, candidate_gradient) = self.compute_full_batch_gradient(train_data);
+
+                line_search_steps += 1;
+
+                if candidate_cost <= cost + SUFFICIENT_DECREASE * step_size * slope
+                    || line_search_steps == MAX_LINE_SEARCH_STEPS
+                {
+                    break (candidate, candidate_cost, candidate_gradient);
+                }
+
+                step_size *= 0.5;
+            };
+
+            let decreased = candidate_cost < cost;
+
+            if decreased {
+                let s: Vec<f64> = candidate.iter().zip(&parameters).map(|(new, old)| new - old).collect();
+                let y: Vec<f64> = candidate_gradient.iter().zip(&gradient).map(|(new, old)| new - old).collect();
+                let curvature = Self::dot_product(&s, &y);
+
+                // Only keep pairs which preserve a positive definite approximation
+                if curvature > 1e-10 {
+                    if history.len() == history_size {
+                        history.pop_front();
+                    }
+
+                    history.push_back(CurvaturePair { s, y, rho: 1.0 / curvature });
+                }
+
+                parameters = candidate;
+                cost = candidate_cost;
+                gradient = candidate_gradient;
+            } else {
+                self.set_parameters(&parameters);
+            }
+
+            duration += timestamp.elapsed();
+            self.epoch = iteration;
+
+            // A failed line search along the approximated direction is retried as steepest descent
+            let stop = match decreased {
+                true if Self::dot_product(&gradient, &gradient).sqrt() < tolerance => Some(LbfgsStop::Converged),
+                true => (iteration == max_iterations).then_some(LbfgsStop::MaxIterations),
+                false if history.is_empty() => Some(LbfgsStop::LineSearchFailed),
+                false => {
+                    history.clear();
+                    (iteration == max_iterations).then_some(LbfgsStop::MaxIterations)
+                }
+            };
+
+            let (accuracy, validation_cost) = self.test(validation_data);
+
+            observer.lbfgs_iteration(&LbfgsReport {
+                iteration, 
+                step_size, 
+                accuracy, 
+                cost: validation_cost, 
+                regularization_penalty: self.compute_regularization_penalty(), 
+                duration, 
+                stop
+            });
+
+            if stop.is_some() {
+                break;
+            }
+        }
+    }
+}
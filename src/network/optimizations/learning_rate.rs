@@ -1,68 +1,508 @@
-#![allow(dead_code)]
-
 use crate::network::state::Network;
+use super::optimizer;
+
+use serde::{ Serialize, Deserialize };
+use std::f64::consts::PI;
 
-#[derive(Debug)]
+/// How [`Decay`] lowers the learning rate.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum DecayMethod {
+    /// Multiply with the rate
     Step,
+    /// Multiply with the rate to the power of the epoch
     Exponential,
+    /// Divide by '1 + rate * epoch'
     Inverse
 }
 
 use DecayMethod::*;
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Lowers the learning rate every few epochs.
 pub struct Decay {
+    /// How the learning rate is lowered
     pub method: DecayMethod,
+    /// Decay rate of the method
     pub rate: f64,
-    pub step: u32    
+    /// Epochs between two decays
+    pub step: u32
 }
 
 impl Decay {
     fn decay(decay: &Option<Self>, alpha: &mut f64, adjusted_epoch: u32) {
         if let Some(decay) = decay {
-            if adjusted_epoch % decay.step == 0 {
+            if adjusted_epoch.is_multiple_of(decay.step) {
                 match decay.method {
                     Step => *alpha *= decay.rate,
                     Exponential => *alpha *= decay.rate.powi(adjusted_epoch as i32),
                     Inverse => *alpha /= 1.0 + decay.rate * adjusted_epoch as f64,
-                }            
+                }
             }
-        }        
+        }
     }
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Resets the learning rate every few epochs.
 pub struct Restart {
+    /// Epochs between two restarts
     pub interval: u32,
+    /// Learning rate after a restart
     pub alpha: f64,
 }
 
 impl Restart {
     fn restart(restart: &Option<Self>, alpha: &mut f64, epoch: &u32) {
         if let Some(restart) = &restart {
-            if *epoch % restart.interval == 0 {
+            if epoch.is_multiple_of(restart.interval) {
                 *alpha = restart.alpha;
-            }   
-        }        
+            }
+        }
+    }
+}
+
+/// Cosine annealing with warm restarts (SGDR).
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CosineAnnealingHyperParams {
+    /// Learning rate at the end of each cycle
+    pub min_alpha: f64,
+    /// Length of the first cycle in epochs
+    pub period: u32,
+    /// Each cycle is this many times longer than the previous one
+    pub period_multiplier: u32
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// One cycle up to a peak learning rate and back down below the initial one.
+pub struct OneCycleHyperParams {
+    /// Peak learning rate
+    pub max_alpha: f64,
+    /// Length of the whole cycle in epochs
+    pub epochs: u32,
+    /// Fraction of the cycle spent increasing the learning rate
+    pub warmup_fraction: f64,
+    /// The cycle ends at 'alpha / final_divisor'
+    pub final_divisor: f64
+}
+
+/// Linear oscillation between 'alpha' and 'max_alpha'.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriangularHyperParams {
+    /// Upper bound of the learning rate
+    pub max_alpha: f64,
+    /// Epochs from 'alpha' to 'max_alpha'
+    pub half_period: u32
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Lowers the learning rate once the validation cost stops improving.
+pub struct ReduceOnPlateauHyperParams {
+    /// Multiplied with the learning rate on each reduction
+    pub factor: f64,
+    /// Number of epochs without improvement of the validation cost before reducing the learning rate
+    pub patience: u32,
+    /// Minimum decrease of the validation cost that counts as improvement
+    pub min_delta: f64,
+    /// Lower bound of the learning rate
+    pub min_alpha: f64
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Schedule of the learning rate over the epochs.
+pub enum SchedulerType {
+    /// Keeps the initial learning rate
+    Constant,
+    /// Optional decay and restarts
+    Decay {
+        /// Lowers the learning rate if set
+        decay: Option<Decay>,
+        /// Resets the learning rate if set
+        restart: Option<Restart>
+    },
+    /// Cosine annealing with warm restarts
+    CosineAnnealing(CosineAnnealingHyperParams),
+    /// One-cycle policy
+    OneCycle(OneCycleHyperParams),
+    /// Triangular cyclic learning rate
+    Triangular(TriangularHyperParams),
+    /// Reduce on plateau of the validation cost
+    ReduceOnPlateau(ReduceOnPlateauHyperParams)
+}
+
+impl SchedulerType {
+    pub(crate) fn get(&self, alpha: f64) -> Box<dyn Scheduler> {
+        match self {
+            Self::Constant => Box::new(ConstantScheduler { alpha }),
+            Self::Decay { decay, restart } => Box::new(DecayScheduler { decay: *decay, restart: *restart, alpha }),
+            Self::CosineAnnealing(hyper_params) => Box::new(CosineAnnealing { hyper_params: *hyper_params, alpha }),
+            Self::OneCycle(hyper_params) => Box::new(OneCycle { hyper_params: *hyper_params, alpha }),
+            Self::Triangular(hyper_params) => Box::new(Triangular { hyper_params: *hyper_params, alpha }),
+            Self::ReduceOnPlateau(hyper_params) => Box::new(ReduceOnPlateau {
+                hyper_params: *hyper_params,
+                alpha,
+                best_cost: f64::INFINITY,
+                bad_epochs: 0
+            })
+        }
+    }
+
+    // Whether the learning rate runs through cycles, required by SWA over cycles
+    pub(crate) fn is_cyclic(&self) -> bool {
+        match self {
+            Self::Decay { restart, .. } => restart.is_some(),
+            Self::CosineAnnealing(_) | Self::Triangular(_) => true,
+            _ => false
+        }
+    }
+}
+
+pub trait Scheduler: Send {
+    // Learning rate of the next optimizer step, 'epochs' includes the completed fraction of the current epoch
+    fn get_alpha(&mut self, epochs: f64) -> f64;
+
+    // Called at the end of every epoch with the validation cost
+    fn end_epoch(&mut self, _epoch: u32, _validation_cost: f64) {}
+
+    // Whether a learning rate cycle ends with this epoch
+    fn is_cycle_end(&self, _epoch: u32) -> bool {
+        false
+    }
+
+    // State changed by 'end_epoch', written to checkpoints
+    fn state(&self) -> Vec<f64> {
+        Vec::new()
+    }
+
+    fn set_state(&mut self, _state: &mut dyn Iterator<Item = f64>) {}
+}
+
+pub struct ConstantScheduler {
+    pub alpha: f64
+}
+
+impl Scheduler for ConstantScheduler {
+    fn get_alpha(&mut self, _: f64) -> f64 {
+        self.alpha
+    }
+}
+
+pub struct DecayScheduler {
+    pub decay: Option<Decay>,
+    pub restart: Option<Restart>,
+    pub alpha: f64
+}
+
+impl Scheduler for DecayScheduler {
+    fn get_alpha(&mut self, _: f64) -> f64 {
+        self.alpha
+    }
+
+    fn end_epoch(&mut self, epoch: u32, _: f64) {
+        let mut adjusted_epoch = epoch;
+
+        if let Some(restart) = &self.restart {
+            adjusted_epoch = epoch % restart.interval;
+        }
+
+        Decay::decay(&self.decay, &mut self.alpha, adjusted_epoch);
+        Restart::restart(&self.restart, &mut self.alpha, &epoch);
+    }
+
+    fn is_cycle_end(&self, epoch: u32) -> bool {
+        self.restart.as_ref().is_some_and(|restart| epoch.is_multiple_of(restart.interval))
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![self.alpha]
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.alpha = optimizer::read_value(state);
+    }
+}
+
+// Interpolate from 'start' to 'end' along half a cosine wave, 'progress' ranges from 0 to 1
+fn cosine_interpolation(start: f64, end: f64, progress: f64) -> f64 {
+    end + (start - end) * 0.5 * (1.0 + (PI * progress).cos())
+}
+
+pub struct CosineAnnealing {
+    pub hyper_params: CosineAnnealingHyperParams,
+    pub alpha: f64
+}
+
+impl CosineAnnealing {
+    // Start and length of the cycle containing 'epochs'
+    fn get_cycle(&self, epochs: f64) -> (f64, f64) {
+        let CosineAnnealingHyperParams { period, period_multiplier, .. } = self.hyper_params;
+
+        let mut start = 0.0;
+        let mut length = period.max(1) as f64;
+
+        while epochs >= start + length {
+            start += length;
+            length *= period_multiplier.max(1) as f64;
+        }
+
+        (start, length)
+    }
+}
+
+impl Scheduler for CosineAnnealing {
+    fn get_alpha(&mut self, epochs: f64) -> f64 {
+        let (start, length) = self.get_cycle(epochs);
+
+        cosine_interpolation(self.alpha, self.hyper_params.min_alpha, (epochs - start) / length)
+    }
+
+    fn is_cycle_end(&self, epoch: u32) -> bool {
+        self.get_cycle(epoch as f64).0 == epoch as f64
+    }
+}
+
+pub struct OneCycle {
+    pub hyper_params: OneCycleHyperParams,
+    pub alpha: f64
+}
+
+impl Scheduler for OneCycle {
+    fn get_alpha(&mut self, epochs: f64) -> f64 {
+        let OneCycleHyperParams { max_alpha, epochs: cycle_epochs, warmup_fraction, final_divisor } = self.hyper_params;
+
+        let progress = (epochs / cycle_epochs as f64).min(1.0);
+
+        match progress < warmup_fraction {
+            true => cosine_interpolation(self.alpha, max_alpha, progress / warmup_fraction),
+            false => cosine_interpolation(
+                max_alpha,
+                self.alpha / final_divisor,
+                (progress - warmup_fraction) / (1.0 - warmup_fraction)
+            )
+        }
+    }
+}
+
+pub struct Triangular {
+    pub hyper_params: TriangularHyperParams,
+    pub alpha: f64
+}
+
+impl Scheduler for Triangular {
+    fn get_alpha(&mut self, epochs: f64) -> f64 {
+        let TriangularHyperParams { max_alpha, half_period } = self.hyper_params;
+
+        // Distance to the peak of the current cycle, 1 at the start and end of a cycle
+        let distance = (epochs / half_period as f64 % 2.0 - 1.0).abs();
+
+        self.alpha + (max_alpha - self.alpha) * (1.0 - distance)
+    }
+
+    fn is_cycle_end(&self, epoch: u32) -> bool {
+        epoch.is_multiple_of(2 * self.hyper_params.half_period)
+    }
+}
+
+pub struct ReduceOnPlateau {
+    pub hyper_params: ReduceOnPlateauHyperParams,
+    pub alpha: f64,
+    pub best_cost: f64,
+    pub bad_epochs: u32
+}
+
+impl Scheduler for ReduceOnPlateau {
+    fn get_alpha(&mut self, _: f64) -> f64 {
+        self.alpha
+    }
+
+    fn end_epoch(&mut self, _: u32, validation_cost: f64) {
+        let ReduceOnPlateauHyperParams { factor, patience, min_delta, min_alpha } = self.hyper_params;
+
+        if validation_cost < self.best_cost - min_delta {
+            self.best_cost = validation_cost;
+            self.bad_epochs = 0;
+        } else {
+            self.bad_epochs += 1;
+        }
+
+        if self.bad_epochs > patience {
+            self.alpha = (self.alpha * factor).max(min_alpha);
+            self.bad_epochs = 0;
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![self.alpha, self.best_cost, self.bad_epochs as f64]
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.alpha = optimizer::read_value(state);
+        self.best_cost = optimizer::read_value(state);
+        self.bad_epochs = optimizer::read_value(state) as u32;
     }
 }
 
+/// How often the scheduled learning rate changes.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Interval {
+    /// With every optimizer step
+    Step,
+    /// Once per epoch
+    Epoch
+}
+
+/// Linear warmup at the start of training.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Warmup {
+    /// Number of optimizer steps over which the learning rate rises linearly from 0
+    pub steps: u32
+}
+
+/// Learning rate and its schedule.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct LearningRate {
+    /// Initial learning rate, holds the current learning rate during training
     pub alpha: f64,
-    pub restart: Option<Restart>,
-    pub decay: Option<Decay>
+    /// Schedule over the epochs
+    pub scheduler: SchedulerType,
+    /// Change the scheduled learning rate with every optimizer step or once per epoch
+    pub interval: Interval,
+    /// Applied on top of the schedule if set
+    pub warmup: Option<Warmup>
 }
 
 impl LearningRate {
-    pub fn update(network: &mut Network, epoch: &u32) {
-        let LearningRate { alpha, decay, restart } = &mut network.hyper_params.learning_rate;
+    // Called before every optimizer step, 'batch' is the index of the batch within the current epoch
+    pub(crate) fn update(network: &mut Network, epoch: u32, batch: usize, batches: usize) {
+        let LearningRate { interval, warmup, .. } = network.hyper_params.learning_rate;
 
-        let mut adjusted_epoch = *epoch;
+        let epochs = match interval {
+            Interval::Step => (epoch - 1) as f64 + batch as f64 / batches as f64,
+            Interval::Epoch => (epoch - 1) as f64
+        };
 
-        if let Some(restart) = &restart {
-            adjusted_epoch = epoch % restart.interval;
+        let mut alpha = network.scheduler.get_alpha(epochs);
+
+        // Warmup is applied per step regardless of the interval
+        if let Some(Warmup { steps }) = warmup {
+            let step = network.optimizer.iteration() + 1;
+            alpha *= (step as f64 / steps as f64).min(1.0);
+        }
+
+        network.hyper_params.learning_rate.alpha = alpha;
+    }
+
+    pub(crate) fn end_epoch(network: &mut Network, epoch: u32, validation_cost: f64) {
+        network.scheduler.end_epoch(epoch, validation_cost);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ dataset::Normalization, network::{ state::HyperParams, optimizations::activations::ActivationType::* } };
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn decay_lowers_every_step_epochs() {
+        let decay = Decay { method: Step, rate: 0.5, step: 2 };
+        let mut scheduler = SchedulerType::Decay { decay: Some(decay), restart: None }.get(1.0);
+
+        scheduler.end_epoch(1, 0.0);
+        assert_close(scheduler.get_alpha(1.0), 1.0);
+
+        scheduler.end_epoch(2, 0.0);
+        assert_close(scheduler.get_alpha(2.0), 0.5);
+    }
+
+    #[test]
+    fn cosine_annealing_restarts_with_longer_cycles() {
+        let hyper_params = CosineAnnealingHyperParams { min_alpha: 0.0, period: 2, period_multiplier: 2 };
+        let mut scheduler = SchedulerType::CosineAnnealing(hyper_params).get(1.0);
+
+        // Cycles of 2 and 4 epochs, each one starting at 'alpha'
+        for (epochs, alpha) in [(0.0, 1.0), (1.0, 0.5), (2.0, 1.0), (4.0, 0.5), (6.0, 1.0)] {
+            assert_close(scheduler.get_alpha(epochs), alpha);
+        }
+
+        assert!(scheduler.is_cycle_end(2) && scheduler.is_cycle_end(6));
+        assert!(!scheduler.is_cycle_end(4));
+    }
+
+    #[test]
+    fn one_cycle_peaks_after_warmup_and_ends_below_alpha() {
+        let hyper_params = OneCycleHyperParams { max_alpha: 1.0, epochs: 10, warmup_fraction: 0.3, final_divisor: 10.0 };
+        let mut scheduler = SchedulerType::OneCycle(hyper_params).get(0.1);
+
+        for (epochs, alpha) in [(0.0, 0.1), (3.0, 1.0), (10.0, 0.01), (20.0, 0.01)] {
+            assert_close(scheduler.get_alpha(epochs), alpha);
+        }
+    }
+
+    #[test]
+    fn triangular_oscillates_linearly() {
+        let mut scheduler = SchedulerType::Triangular(TriangularHyperParams { max_alpha: 1.1, half_period: 2 }).get(0.1);
+
+        for (epochs, alpha) in [(0.0, 0.1), (1.0, 0.6), (2.0, 1.1), (3.0, 0.6), (4.0, 0.1)] {
+            assert_close(scheduler.get_alpha(epochs), alpha);
+        }
+
+        assert!(scheduler.is_cycle_end(4));
+        assert!(!scheduler.is_cycle_end(2));
+    }
+
+    #[test]
+    fn reduce_on_plateau_reduces_after_patience_and_resumes() {
+        let hyper_params = ReduceOnPlateauHyperParams { factor: 0.5, patience: 1, min_delta: 0.0, min_alpha: 0.3 };
+        let mut scheduler = SchedulerType::ReduceOnPlateau(hyper_params).get(1.0);
+
+        // An improvement followed by two epochs without one
+        for (epoch, cost) in [(1, 1.0), (2, 1.0), (3, 1.0)] {
+            scheduler.end_epoch(epoch, cost);
         }
 
-        Decay::decay(decay, alpha, adjusted_epoch);
-        Restart::restart(restart, alpha, epoch);
+        assert_close(scheduler.get_alpha(3.0), 0.5);
+
+        // A scheduler restored from the state continues identically
+        let mut resumed = SchedulerType::ReduceOnPlateau(hyper_params).get(1.0);
+        resumed.set_state(&mut scheduler.state().into_iter());
+
+        assert_eq!(resumed.state(), scheduler.state());
+
+        for scheduler in [&mut scheduler, &mut resumed] {
+            scheduler.end_epoch(4, 1.0);
+            scheduler.end_epoch(5, 1.0);
+
+            // Bounded by 'min_alpha'
+            assert_close(scheduler.get_alpha(5.0), 0.3);
+        }
+    }
+
+    #[test]
+    fn warmup_rises_linearly_per_step() {
+        let mut hyper_params = HyperParams::inference(vec![2, 2], &[Sigmoid], Normalization::GRAYSCALE);
+        hyper_params.learning_rate.alpha = 1.0;
+        hyper_params.learning_rate.warmup = Some(Warmup { steps: 4 });
+
+        let mut network = Network::new(hyper_params).unwrap();
+
+        // The gradients are zero, so the steps only count the iterations
+        for alpha in [0.25, 0.5, 0.75, 1.0, 1.0] {
+            LearningRate::update(&mut network, 1, 0, 1);
+            assert_close(network.hyper_params.learning_rate.alpha, alpha);
+
+            network.step();
+        }
     }
 }
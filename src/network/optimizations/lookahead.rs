@@ -0,0 +1,88 @@
+use crate::network::{ state::Vec2D, utils };
+use super::optimizer::{ self, Optimizer, Parameters };
+
+use serde::{ Serialize, Deserialize };
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Slow weights that follow the weights of the wrapped optimizer.
+pub struct LookaheadHyperParams {
+    /// Number of fast steps of the inner optimizer between two synchronizations ('k')
+    pub steps: u32,
+    /// Interpolation factor of the slow weights towards the fast weights
+    pub alpha: f64
+}
+
+// Wraps any optimizer, the network's weights and biases act as the fast weights
+pub struct Lookahead {
+    pub hyper_params: LookaheadHyperParams,
+    pub inner: Box<dyn Optimizer>,
+    // Zeros until the first step, so the state written to checkpoints always has the same length
+    pub slow: Parameters,
+    pub started: bool,
+    pub fast_steps: u32
+}
+
+impl Lookahead {
+    pub fn new(hyper_params: LookaheadHyperParams, inner: Box<dyn Optimizer>, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            inner,
+            slow: Parameters::new(composition),
+            started: false,
+            fast_steps: 0
+        }
+    }
+}
+
+impl Optimizer for Lookahead {
+    fn compute_steps(&mut self, weights: &[Vec2D], biases: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        self.inner.compute_steps(weights, biases, gradients, alpha);
+
+        // Slow weights start at the parameters before the first step, which may have been loaded after 'new'
+        if !self.started {
+            self.slow = Parameters { weights: weights.to_vec(), biases: biases.to_vec() };
+            self.started = true;
+        }
+
+        self.fast_steps += 1;
+
+        if self.fast_steps < self.hyper_params.steps {
+            return;
+        }
+
+        self.fast_steps = 0;
+
+        // Move the slow weights towards the fast weights and reset the fast weights onto them
+        for ((step, slow), parameter) in gradients.iter_mut()
+            .zip(self.slow.iter_mut())
+            .zip(utils::flatten(weights, biases))
+        {
+            let fast = parameter - *step;
+
+            *slow += self.hyper_params.alpha * (fast - *slow);
+            *step = parameter - *slow;
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.inner.iteration()
+    }
+
+    fn state(&self) -> Vec<f64> {
+        let mut state = self.inner.state();
+
+        state.push(self.fast_steps as f64);
+        state.push(self.started as u8 as f64);
+        state.extend(self.slow.iter().copied());
+
+        state
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.inner.set_state(state);
+        self.fast_steps = optimizer::read_value(state) as u32;
+        self.started = optimizer::read_value(state) != 0.0;
+        self.slow.read(state);
+    }
+}
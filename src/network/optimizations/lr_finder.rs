@@ -0,0 +1,119 @@
+use crate::{ dataset::Data, network::state::Network };
+use super::{
+    regularization::Dropout, batch::Batch, gradient_clipping::GradientClipping, weight_averaging::WeightAverages
+};
+
+/// The learning rate grows exponentially from 'min_alpha' to 'max_alpha' over 'steps' mini-batches.
+#[derive(Clone, Copy)]
+pub struct LrFinderHyperParams {
+    /// Learning rate of the first step
+    pub min_alpha: f64,
+    /// Learning rate of the last step
+    pub max_alpha: f64,
+    /// Number of mini-batch steps
+    pub steps: u32,
+    /// Factor of the exponential moving average of the loss
+    pub smoothing: f64
+}
+
+// Stop the range test once the smoothed loss exceeds the lowest smoothed loss by this factor
+const DIVERGENCE_FACTOR: f64 = 4.0;
+// Fraction of the first steps ignored for the suggestion, the loss of an untrained network drops at any learning rate
+const SKIP_FRACTION: f64 = 0.1;
+
+/// Result of a learning rate range test.
+pub struct LrFinder {
+    /// Learning rate of each step until the loss diverged
+    pub alphas: Vec<f64>,
+    /// Smoothed training loss after each step, same length as 'alphas'
+    pub losses: Vec<f64>,
+    /// Learning rate at the steepest descent of the smoothed loss
+    pub suggestion: Option<f64>
+}
+
+impl LrFinder {
+    fn suggest(alphas: &[f64], losses: &[f64]) -> Option<f64> {
+        let skip = ((alphas.len() as f64 * SKIP_FRACTION) as usize).max(1);
+
+        // Slope of the loss with respect to the logarithm of the learning rate
+        (skip..alphas.len())
+            .map(|step| {
+                let slope = (losses[step] - losses[step - 1]) / (alphas[step].ln() - alphas[step - 1].ln());
+                (step, slope)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(step, _)| alphas[step - 1])
+    }
+}
+
+impl Network {
+    /// Learning rate range test, the parameters and the optimizer state are reset afterwards.
+    pub fn find_learning_rate(&mut self, train_data: &Data, hyper_params: LrFinderHyperParams) -> LrFinder {
+        let LrFinderHyperParams { min_alpha, max_alpha, steps, smoothing } = hyper_params;
+
+        let weights = self.weights.clone();
+        let biases = self.biases.clone();
+        let alpha = self.hyper_params.learning_rate.alpha;
+
+        let mut alphas = Vec::with_capacity(steps as usize);
+        let mut losses = Vec::with_capacity(steps as usize);
+
+        let mut average_loss = 0.0;
+        let mut best_loss = f64::INFINITY;
+
+        // Repeat the training data if it has fewer batches than steps
+        let batches = train_data.inputs.chunks(self.hyper_params.batch_size)
+            .zip(train_data.targets.chunks(self.hyper_params.batch_size))
+            .cycle()
+            .take(steps as usize);
+
+        for (step, (inputs, targets)) in batches.enumerate() {
+            let progress = step as f64 / (steps - 1).max(1) as f64;
+            let alpha = min_alpha * (max_alpha / min_alpha).powf(progress);
+
+            self.hyper_params.learning_rate.alpha = alpha;
+            Dropout::generate_mask(self);
+
+            let mut loss = 0.0;
+
+            for (inputs, targets) in inputs.iter().zip(targets.iter()) {
+                self.forward(inputs);
+                self.backward(targets);
+                Batch::accumulate(self);
+
+                loss += Self::compute_cost(self.outputs.last().unwrap(), targets);
+            }
+
+            Batch::average(self, inputs.len() as f64);
+            GradientClipping::clip(self);
+            self.step();
+
+            // Bias corrected moving average of the batch losses
+            average_loss = smoothing * average_loss + (1.0 - smoothing) * loss / inputs.len() as f64;
+            let smoothed_loss = average_loss / (1.0 - smoothing.powi(step as i32 + 1));
+
+            if !smoothed_loss.is_finite() || smoothed_loss > DIVERGENCE_FACTOR * best_loss {
+                break;
+            }
+
+            best_loss = best_loss.min(smoothed_loss);
+
+            alphas.push(alpha);
+            losses.push(smoothed_loss);
+        }
+
+        Dropout::set_all_active_mask(self);
+
+        self.weights = weights;
+        self.biases = biases;
+        self.hyper_params.learning_rate.alpha = alpha;
+        self.optimizer = self.hyper_params.optimizer.get(self.hyper_params.lookahead, &self.hyper_params.composition);
+        self.averages = WeightAverages::new();
+
+        LrFinder {
+            suggestion: LrFinder::suggest(&alphas, &losses),
+            alphas,
+            losses
+        }
+    }
+}
@@ -1,6 +1,17 @@
 pub mod activations;
+pub mod optimizer;
+pub mod sgd;
+pub mod rmsprop;
+pub mod adagrad;
 pub mod adam;
+pub mod lamb;
+pub mod lars;
+pub mod lbfgs;
+pub mod lookahead;
+pub mod lr_finder;
 pub mod batch;
 pub mod early_stopping;
+pub mod gradient_clipping;
 pub mod learning_rate;
 pub mod regularization;
+pub mod weight_averaging;
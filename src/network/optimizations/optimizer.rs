@@ -0,0 +1,145 @@
+use crate::network::{ state::{ Vec2D, Network }, utils };
+use super::{
+    regularization::MaxNormConstraint,
+    adam::{ AdamHyperParams, AdamWHyperParams, Adam },
+    sgd::{ MomentumHyperParams, Sgd, Momentum, Nesterov },
+    rmsprop::{ RmsPropHyperParams, RmsProp },
+    adagrad::{ AdaGradHyperParams, AdaGrad },
+    lamb::{ LambHyperParams, Lamb },
+    lars::{ LarsHyperParams, Lars },
+    lookahead::{ LookaheadHyperParams, Lookahead },
+    weight_averaging::WeightAverages
+};
+
+use serde::{ Serialize, Deserialize };
+
+// Per-parameter values shaped like the weights and biases of the network
+#[derive(Clone)]
+pub struct Parameters {
+    pub weights: Vec<Vec2D>,
+    pub biases: Vec2D
+}
+
+impl Parameters {
+    pub(crate) fn new(composition: &[usize]) -> Self {
+        Self {
+            weights: utils::zeros_3d_vec(composition),
+            biases: utils::zeros_2d_vec(composition, 1)
+        }
+    }
+
+    // Weights first, then biases, same order as 'utils::flatten'
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &f64> {
+        utils::flatten(&self.weights, &self.biases)
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut f64> {
+        self.weights.iter_mut()
+            .flat_map(|layer| layer.iter_mut())
+            .flat_map(|neuron| neuron.iter_mut())
+            .chain(self.biases.iter_mut().flat_map(|layer| layer.iter_mut()))
+    }
+
+    // Overwrite all values with the next values of a checkpoint state
+    pub(crate) fn read(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        for value in self.iter_mut() {
+            *value = read_value(state);
+        }
+    }
+}
+
+pub fn read_value(state: &mut dyn Iterator<Item = f64>) -> f64 {
+    state.next().expect("ERROR: incomplete checkpoint")
+}
+
+pub trait Optimizer: Send {
+    // Replaces the gradients with the steps which are subtracted from the parameters
+    fn compute_steps(
+        &mut self, weights: &[Vec2D], biases: &[Vec<f64>], gradients: &mut Parameters, alpha: f64
+    );
+
+    // Number of steps taken so far
+    fn iteration(&self) -> i32;
+
+    // Internal state written to checkpoints, starting with the iteration
+    fn state(&self) -> Vec<f64>;
+
+    // Restore the internal state in the order written by 'state'
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>);
+}
+
+// Layer-wise ratio used by LAMB and LARS, falls back to 1 if a layer or its update is all zeros
+pub fn trust_ratio(weight_norm: f64, update_norm: f64) -> f64 {
+    match weight_norm > 0.0 && update_norm > 0.0 {
+        true => weight_norm / update_norm,
+        false => 1.0
+    }
+}
+
+/// Optimizer of the weights and biases.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum OptimizerType {
+    /// Stochastic gradient descent
+    Sgd,
+    /// Gradient descent with momentum
+    Momentum(MomentumHyperParams),
+    /// Nesterov accelerated gradient
+    Nesterov(MomentumHyperParams),
+    /// RMSProp
+    RmsProp(RmsPropHyperParams),
+    /// AdaGrad
+    AdaGrad(AdaGradHyperParams),
+    /// Adam, optionally AMSGrad
+    Adam(AdamHyperParams),
+    /// Adam with decoupled weight decay
+    AdamW(AdamWHyperParams),
+    /// LAMB
+    Lamb(LambHyperParams),
+    /// LARS
+    Lars(LarsHyperParams)
+}
+
+impl OptimizerType {
+    // Optionally wrapped by Lookahead
+    pub(crate) fn get(&self, lookahead: Option<LookaheadHyperParams>, composition: &[usize]) -> Box<dyn Optimizer> {
+        let optimizer = self.get_inner(composition);
+
+        match lookahead {
+            Some(hyper_params) => Box::new(Lookahead::new(hyper_params, optimizer, composition)),
+            None => optimizer
+        }
+    }
+
+    fn get_inner(&self, composition: &[usize]) -> Box<dyn Optimizer> {
+        match self {
+            Self::Sgd => Box::new(Sgd::new()),
+            Self::Momentum(hyper_params) => Box::new(Momentum::new(*hyper_params, composition)),
+            Self::Nesterov(hyper_params) => Box::new(Nesterov::new(*hyper_params, composition)),
+            Self::RmsProp(hyper_params) => Box::new(RmsProp::new(*hyper_params, composition)),
+            Self::AdaGrad(hyper_params) => Box::new(AdaGrad::new(*hyper_params, composition)),
+            Self::Adam(hyper_params) => Box::new(Adam::new(*hyper_params, composition)),
+            Self::AdamW(hyper_params) => Box::new(Adam::with_weight_decay(*hyper_params, composition)),
+            Self::Lamb(hyper_params) => Box::new(Lamb::new(*hyper_params, composition)),
+            Self::Lars(hyper_params) => Box::new(Lars::new(*hyper_params, composition))
+        }
+    }
+}
+
+impl Network {
+    // Take one optimizer step with the gradients in 'self.gradients'
+    pub(crate) fn step(&mut self) {
+        let Network { weights, biases, gradients, optimizer, hyper_params, .. } = self;
+
+        optimizer.compute_steps(weights, biases, gradients, hyper_params.learning_rate.alpha);
+
+        for (parameter, step) in weights.iter_mut().flatten().flatten()
+            .chain(biases.iter_mut().flatten())
+            .zip(gradients.iter())
+        {
+            *parameter -= step;
+        }
+
+        MaxNormConstraint::apply(self);
+        WeightAverages::update_ema(self);
+    }
+}
@@ -1,71 +1,119 @@
-use rand::{ thread_rng, Rng };
+use rand::Rng;
+use serde::{ Serialize, Deserialize };
 
 use crate::network::{ state::{ Network, Vec2D }, utils };
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Penalty factors of the elastic net.
 pub struct ElasticNetRegularizer {
+    /// Factor of the absolute values
     pub l1: f64,
+    /// Factor of the squared values
     pub l2: f64
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// L1 and L2 penalty of the weights and biases.
 pub struct ElasticNetRegularization {
+    /// Penalty of the weights
     pub weights: ElasticNetRegularizer,
+    /// Penalty of the biases
     pub biases: ElasticNetRegularizer    
 }
 
 // 'impl Network' because 'impl ElasticNetRegularization' 
 // significantly reduces performance during backpropagation
 impl Network {
-    pub fn elastic_net_regularization(
+    pub(crate) fn elastic_net_penalty(
         ElasticNetRegularizer { l1, l2 }: &ElasticNetRegularizer, value: f64
     ) -> f64 {       
         l1 * value.abs() + l2 * value.powi(2)
     }
+
+    // Derivative of the penalty, the subgradient of '|value|' at 0 is taken as 0
+    pub(crate) fn elastic_net_gradient(
+        ElasticNetRegularizer { l1, l2 }: &ElasticNetRegularizer, value: f64
+    ) -> f64 {
+        let sign = match value == 0.0 {
+            true => 0.0,
+            false => value.signum()
+        };
+
+        l1 * sign + 2.0 * l2 * value
+    }
+
+    /// Elastic net penalty of all weights and biases, part of the reported cost.
+    pub fn compute_regularization_penalty(&self) -> f64 {
+        let ElasticNetRegularization { weights, biases } = &self.hyper_params.regularization.elastic_net;
+
+        let weights_penalty = self.weights.iter().flatten().flatten()
+            .map(|&weight| Self::elastic_net_penalty(weights, weight))
+            .sum::<f64>();
+
+        let biases_penalty = self.biases.iter().flatten()
+            .map(|&bias| Self::elastic_net_penalty(biases, bias))
+            .sum::<f64>();
+
+        weights_penalty + biases_penalty
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Probabilities of dropping out a neuron while training.
 pub struct Dropout {
+    /// Dropout rate of the input layer
     pub input_layer: f64,
+    /// Dropout rate of the hidden layers
     pub hidden_layer: f64,
 }
 
 impl Dropout {
-    pub fn init_mask(composition: &[usize]) -> Vec2D {
-        let mut dropout_mask = utils::zeros_2d_vec(composition, 0);
+    // All inputs and neurons start active, so untrained and loaded networks predict without dropout.
+    // The neuron mask has one entry for each neuron of the hidden and output layers.
+    pub(crate) fn init_masks(composition: &[usize]) -> (Vec<f64>, Vec2D) {
+        let input_dropout_mask = vec![1.0; composition[0]];
+        let mut dropout_mask = utils::zeros_2d_vec(composition, 1);
         
-        // Output layer is never dropped out
-        if let Some(output_layer_mask) = dropout_mask.last_mut() {
-            for mask in output_layer_mask.iter_mut() {
+        // The output layer is never dropped out, 'generate_mask' skips it
+        for layer in dropout_mask.iter_mut() {
+            for mask in layer.iter_mut() {
                 *mask = 1.0;
             }
         }
 
-        dropout_mask
+        (input_dropout_mask, dropout_mask)
     }
     
-    pub fn generate_mask(network: &mut Network) {
+    pub(crate) fn generate_mask(network: &mut Network) {
         let Regularization { dropout_rate, .. } = &network.hyper_params.regularization;
         
-        let mut rng = thread_rng();
-
         // Apply inverse dropout while training, multiplied with binary dropout 
         // mask to save on conditional statements during forwardpropagation
         let input_layer_factor = 1.0 / (1.0 - dropout_rate.input_layer);
         let hidden_layer_factor = 1.0 / (1.0 - dropout_rate.hidden_layer);
 
-        for mask in network.dropout_mask[0].iter_mut() {
-            *mask = rng.gen_bool(1.0 - dropout_rate.input_layer) as u16 as f64 * input_layer_factor;
+        for mask in network.input_dropout_mask.iter_mut() {
+            *mask = network.rng.gen_bool(1.0 - dropout_rate.input_layer) as u16 as f64 * input_layer_factor;
         }
 
-        for layer in 1..network.dropout_mask.len() - 1 {
+        for layer in 0..network.dropout_mask.len() - 1 {
             for neuron in 0..network.dropout_mask[layer].len() {
                 network.dropout_mask[layer][neuron] = 
-                    rng.gen_bool(1.0 - dropout_rate.hidden_layer) as u16 as f64 * 
+                    network.rng.gen_bool(1.0 - dropout_rate.hidden_layer) as u16 as f64 * 
                     hidden_layer_factor;
             }
         }
     }
 
     // Enable all neurons, used before evaluating / testing the network
-    pub fn set_all_active_mask(network: &mut Network) {
+    pub(crate) fn set_all_active_mask(network: &mut Network) {
+        for mask in network.input_dropout_mask.iter_mut() {
+            *mask = 1.0;
+        }
+
         for layer in 0..network.dropout_mask.len() - 1 {
             for neuron in 0..network.dropout_mask[layer].len() {
                 network.dropout_mask[layer][neuron] = 1.0;
@@ -75,16 +123,140 @@ impl Dropout {
 
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Limits the norm of the incoming weights of each neuron.
+pub struct MaxNormConstraint {
+    /// Maximum L2 norm of the incoming weights of a neuron
+    pub limit: f64,
+    /// Optional limit for each hidden and output layer, overrides 'limit'
+    pub layer_limits: Option<Vec<f64>>
+}
+
+impl MaxNormConstraint {
+    pub(crate) fn get_limit(&self, layer: usize) -> f64 {
+        match &self.layer_limits {
+            Some(layer_limits) => layer_limits[layer],
+            None => self.limit
+        }
+    }
+
+    // Project the incoming weights of every neuron back onto the norm ball, applied after each optimizer step
+    pub(crate) fn apply(network: &mut Network) {
+        let max_norm_constraint = &network.hyper_params.regularization.max_norm_constraint;
+
+        for (layer, weights) in network.weights.iter_mut().enumerate() {
+            let limit = max_norm_constraint.get_limit(layer);
+
+            for weights in weights.iter_mut() {
+                let current_weight_l2_norm = Regularization::compute_l2_norm(weights);
+
+                if current_weight_l2_norm > limit {
+                    for weight in weights.iter_mut() {
+                        *weight *= limit / current_weight_l2_norm;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Regularization techniques against overfitting.
 pub struct Regularization {
+    /// Penalty added to the cost
     pub elastic_net: ElasticNetRegularization,
+    /// Dropout while training
     pub dropout_rate: Dropout,
-    pub max_norm_constraint: f64
+    /// Constraint applied after each optimizer step
+    pub max_norm_constraint: MaxNormConstraint
 }
 
 impl Regularization {
-    pub fn compute_l2_norm(weights: &[f64]) -> f64 {
+    pub(crate) fn compute_l2_norm(weights: &[f64]) -> f64 {
         let sum_of_squares = weights.iter().map(|&w| w.powi(2)).sum::<f64>();
         
         sum_of_squares.sqrt()
     }
+
+    pub(crate) fn compute_layer_l2_norm(layer: &[Vec<f64>]) -> f64 {
+        let sum_of_squares = layer.iter().flatten().map(|&w| w.powi(2)).sum::<f64>();
+
+        sum_of_squares.sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::{ Data, Normalization },
+        network::{ state::HyperParams, optimizations::activations::ActivationType::* }
+    };
+
+    fn network(layer_limits: Option<Vec<f64>>) -> Network {
+        let mut hyper_params = HyperParams::inference(vec![4, 6, 3], &[LeakyRelu, Sigmoid], Normalization::GRAYSCALE);
+
+        hyper_params.regularization.max_norm_constraint = MaxNormConstraint { limit: 0.5, layer_limits };
+        hyper_params.learning_rate.alpha = 2.0;
+        hyper_params.seed = Some(17);
+
+        Network::new(hyper_params).unwrap()
+    }
+
+    fn data() -> Data {
+        Data {
+            inputs: (0..6).map(|sample| (0..4).map(|pixel| ((sample * 5 + pixel) % 4) as f64 / 3.0).collect()).collect(),
+            targets: (0..6).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    fn assert_bounded(network: &Network) {
+        let max_norm_constraint = &network.hyper_params.regularization.max_norm_constraint;
+
+        for (layer, weights) in network.weights.iter().enumerate() {
+            for weights in weights {
+                assert!(Regularization::compute_l2_norm(weights) <= max_norm_constraint.get_limit(layer) + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn weights_are_bounded_after_every_step() {
+        let data = data();
+
+        // The random initial weights exceed the limits, so every step has to project them
+        for layer_limits in [None, Some(vec![0.3, 0.7])] {
+            let mut network = network(layer_limits);
+
+            for epoch in 1..=3 {
+                network.train_epoch(&data, epoch);
+                assert_bounded(&network);
+            }
+        }
+    }
+
+    #[test]
+    fn projection_keeps_the_direction() {
+        let mut network = network(None);
+
+        let large = vec![1.2, -0.4, 0.0, 0.9];
+        let small = vec![0.1, 0.2, -0.1, 0.0];
+
+        network.weights[0][0] = large.clone();
+        network.weights[0][1] = small.clone();
+
+        MaxNormConstraint::apply(&mut network);
+
+        // Scaled onto the limit, a neuron within the limit is left as is
+        let scale = 0.5 / Regularization::compute_l2_norm(&large);
+
+        for (projected, weight) in network.weights[0][0].iter().zip(large.iter()) {
+            assert!((projected - weight * scale).abs() < 1e-12);
+        }
+
+        assert_eq!(network.weights[0][1], small);
+        assert_bounded(&network);
+    }
 }
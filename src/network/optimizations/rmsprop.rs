@@ -0,0 +1,59 @@
+use crate::network::state::Vec2D;
+use super::optimizer::{ self, Optimizer, Parameters };
+
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Adaptive learning rates from a moving average of the squared gradients.
+pub struct RmsPropHyperParams {
+    /// Decay of the moving average
+    pub beta: f64,
+    /// Added to the denominator for numerical stability
+    pub epsilon: f64
+}
+
+pub struct RmsProp {
+    pub hyper_params: RmsPropHyperParams,
+    pub iteration: i32,
+    pub mean_square: Parameters
+}
+
+impl RmsProp {
+    pub fn new(hyper_params: RmsPropHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            mean_square: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn compute_steps(&mut self, _: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let RmsPropHyperParams { beta, epsilon } = self.hyper_params;
+
+        self.iteration += 1;
+
+        for (gradient, mean_square) in gradients.iter_mut().zip(self.mean_square.iter_mut()) {
+            *mean_square = beta * *mean_square + (1.0 - beta) * gradient.powi(2);
+            *gradient = alpha * *gradient / (mean_square.sqrt() + epsilon);
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.mean_square.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.mean_square.read(state);
+    }
+}
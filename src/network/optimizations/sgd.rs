@@ -0,0 +1,135 @@
+use crate::network::state::Vec2D;
+use super::optimizer::{ self, Optimizer, Parameters };
+
+use serde::{ Serialize, Deserialize };
+use std::iter;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Gradient descent with a velocity, used by momentum and Nesterov momentum.
+pub struct MomentumHyperParams {
+    /// Decay of the velocity
+    pub beta: f64
+}
+
+#[derive(Default)]
+pub struct Sgd {
+    pub iteration: i32
+}
+
+impl Sgd {
+    pub fn new() -> Self {
+        Self { iteration: 0 }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn compute_steps(&mut self, _: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        self.iteration += 1;
+
+        for gradient in gradients.iter_mut() {
+            *gradient *= alpha;
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![self.iteration as f64]
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+    }
+}
+
+pub struct Momentum {
+    pub hyper_params: MomentumHyperParams,
+    pub iteration: i32,
+    pub velocity: Parameters
+}
+
+impl Momentum {
+    pub fn new(hyper_params: MomentumHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            velocity: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn compute_steps(&mut self, _: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let MomentumHyperParams { beta } = self.hyper_params;
+
+        self.iteration += 1;
+
+        for (gradient, velocity) in gradients.iter_mut().zip(self.velocity.iter_mut()) {
+            *velocity = beta * *velocity + *gradient;
+            *gradient = alpha * *velocity;
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.velocity.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.velocity.read(state);
+    }
+}
+
+pub struct Nesterov {
+    pub hyper_params: MomentumHyperParams,
+    pub iteration: i32,
+    pub velocity: Parameters
+}
+
+impl Nesterov {
+    pub fn new(hyper_params: MomentumHyperParams, composition: &[usize]) -> Self {
+        Self {
+            hyper_params,
+            iteration: 0,
+            velocity: Parameters::new(composition)
+        }
+    }
+}
+
+impl Optimizer for Nesterov {
+    fn compute_steps(&mut self, _: &[Vec2D], _: &[Vec<f64>], gradients: &mut Parameters, alpha: f64) {
+        let MomentumHyperParams { beta } = self.hyper_params;
+
+        self.iteration += 1;
+
+        // Look ahead along the updated velocity instead of evaluating the gradient at the shifted weights
+        for (gradient, velocity) in gradients.iter_mut().zip(self.velocity.iter_mut()) {
+            *velocity = beta * *velocity + *gradient;
+            *gradient = alpha * (*gradient + beta * *velocity);
+        }
+    }
+
+    fn iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    fn state(&self) -> Vec<f64> {
+        iter::once(self.iteration as f64)
+            .chain(self.velocity.iter().copied())
+            .collect()
+    }
+
+    fn set_state(&mut self, state: &mut dyn Iterator<Item = f64>) {
+        self.iteration = optimizer::read_value(state) as i32;
+        self.velocity.read(state);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::network::{ state::Network, utils };
+use super::optimizer::Parameters;
+
+use serde::{ Serialize, Deserialize };
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Exponential moving average of the parameters.
+pub struct Ema {
+    /// Weight of the previous average in each update
+    pub decay: f64
+}
+
+/// Parameters averaged by stochastic weight averaging.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SwaSchedule {
+    /// Average the parameters at the end of each of the last N epochs
+    Epochs(usize),
+    /// Average the parameters at the end of each of the last N learning rate cycles
+    Cycles(usize)
+}
+
+/// Kind of average of the parameters.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Averaging {
+    /// Exponential moving average
+    Ema,
+    /// Stochastic weight averaging
+    Swa
+}
+
+/// Averages of the parameters recorded while training.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeightAveraging {
+    /// Exponential moving average, updated after every optimizer step
+    pub ema: Option<Ema>,
+    /// Stochastic weight averaging
+    pub swa: Option<SwaSchedule>,
+    /// Replace the trained parameters with this average once training ends
+    pub apply: Option<Averaging>
+}
+
+// Shadow copies of the weights and biases
+#[derive(Default)]
+pub struct WeightAverages {
+    pub ema: Option<Parameters>,
+    pub swa: VecDeque<Parameters>
+}
+
+impl WeightAverages {
+    pub(crate) fn new() -> Self {
+        Self {
+            ema: None,
+            swa: VecDeque::new()
+        }
+    }
+
+    fn snapshot(network: &Network) -> Parameters {
+        Parameters {
+            weights: network.weights.clone(),
+            biases: network.biases.clone()
+        }
+    }
+
+    pub(crate) fn update_ema(network: &mut Network) {
+        if let Some(Ema { decay }) = network.hyper_params.weight_averaging.ema {
+            let iteration = network.optimizer.iteration() as f64;
+
+            // Lower decay during the first steps, so the average quickly forgets the initialization
+            let decay = decay.min((1.0 + iteration) / (10.0 + iteration));
+
+            match &mut network.averages.ema {
+                Some(ema) => {
+                    for (average, parameter) in ema.iter_mut().zip(utils::flatten(&network.weights, &network.biases)) {
+                        *average = decay * *average + (1.0 - decay) * parameter;
+                    }
+                },
+                None => network.averages.ema = Some(Self::snapshot(network))
+            }
+        }
+    }
+
+    pub(crate) fn update_swa(network: &mut Network, epoch: u32) {
+        if let Some(schedule) = network.hyper_params.weight_averaging.swa {
+            let (count, end_of_cycle) = match schedule {
+                SwaSchedule::Epochs(count) => (count, true),
+                SwaSchedule::Cycles(count) => (count, network.scheduler.is_cycle_end(epoch))
+            };
+
+            if end_of_cycle {
+                let snapshot = Self::snapshot(network);
+                let swa = &mut network.averages.swa;
+
+                swa.push_back(snapshot);
+
+                if swa.len() > count {
+                    swa.pop_front();
+                }
+            }
+        }
+    }
+}
+
+impl Network {
+    pub(crate) fn get_averaged_parameters(&self, averaging: Averaging) -> Option<Parameters> {
+        match averaging {
+            Averaging::Ema => self.averages.ema.clone(),
+            Averaging::Swa => {
+                let mut snapshots = self.averages.swa.iter();
+                let mut average = snapshots.next()?.clone();
+
+                for snapshot in snapshots {
+                    for (sum, parameter) in average.iter_mut().zip(snapshot.iter()) {
+                        *sum += parameter;
+                    }
+                }
+
+                let count = self.averages.swa.len() as f64;
+
+                for sum in average.iter_mut() {
+                    *sum /= count;
+                }
+
+                Some(average)
+            }
+        }
+    }
+
+    // Use the averaged parameters for evaluation and saving, does nothing if no average was recorded
+    pub(crate) fn apply_averaged_parameters(&mut self, averaging: Averaging) {
+        if let Some(Parameters { weights, biases }) = self.get_averaged_parameters(averaging) {
+            self.weights = weights;
+            self.biases = biases;
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use super::{
+    optimizations::{ early_stopping::{ Metric, Direction }, learning_rate::LearningRate },
+    search::SearchSpace,
+    state::{ Network, HyperParams, SeededRng },
+    observer::Observer,
+    config::ConfigError
+};
+use crate::dataset::Data;
+
+use rand::{ Rng, SeedableRng };
+use serde::{ Serialize, Deserialize };
+use std::{ thread, cmp::Ordering };
+
+// Dropout rates are kept below this value when perturbed
+const MAX_DROPOUT_RATE: f64 = 0.9;
+
+/// Population of networks trained in parallel that copies and perturbs its best members, read from a
+/// config file by [`PopulationBasedTraining::load`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PopulationBasedTraining {
+    /// Number of networks, each one is trained on its own thread
+    pub population: usize,
+    /// Epochs of every member
+    pub epochs: u32,
+    /// Epochs between two exploit and explore phases
+    pub interval: u32,
+    /// Fraction of the population replaced by copies of the same fraction of best members
+    pub exploit_fraction: f64,
+    /// Perturbed hyperparameters are multiplied by either '1 - perturbation' or '1 + perturbation'
+    pub perturbation: f64,
+    /// Sampled for the initial population, fields left at 'None' keep the base hyperparameters
+    #[serde(default)]
+    pub space: SearchSpace,
+    /// Validation metric used to rank the members
+    pub metric: Metric,
+    /// Whether lower or higher scores are better
+    pub direction: Direction
+}
+
+/// Network of a population and its validation results.
+pub struct Member {
+    /// Number of the member in the initial population
+    pub id: usize,
+    /// Trained network with the hyperparameters of its lineage
+    pub network: Network,
+    /// Validation accuracy after the last epoch
+    pub accuracy: f64,
+    /// Validation cost after the last epoch
+    pub cost: f64,
+    /// Value of the metric of the population
+    pub score: f64,
+    /// Learning rate at the start of each interval, inherited on exploitation
+    pub schedule: Vec<(u32, f64)>
+}
+
+impl Member {
+    fn train(&mut self, first_epoch: u32, last_epoch: u32, metric: Metric, train_data: &Data, validation_data: &Data) {
+        self.schedule.push((first_epoch, self.network.hyper_params.learning_rate.alpha));
+
+        for epoch in first_epoch..=last_epoch {
+            self.network.train_epoch(train_data, epoch);
+
+            let (accuracy, cost) = self.network.test(validation_data);
+            LearningRate::end_epoch(&mut self.network, epoch, cost);
+
+            self.accuracy = accuracy;
+            self.cost = cost;
+        }
+
+        self.score = metric.score(&mut self.network, self.accuracy, self.cost, validation_data);
+    }
+
+    // Copy the parameters, the optimizer state, the hyperparameters and the schedule of 'source'
+    fn exploit(&mut self, source: &Member, perturbation: f64, rng: &mut SeededRng) -> Result<(), ConfigError> {
+        let mut hyper_params = source.network.hyper_params.clone();
+        hyper_params.seed = Some(rng.gen());
+
+        // The learning rate is constant, so the perturbed alpha is the new learning rate of the copy
+        Self::explore(&mut hyper_params, perturbation, rng);
+
+        let mut network = Network::new(hyper_params)?;
+        network.weights = source.network.weights.clone();
+        network.biases = source.network.biases.clone();
+        network.optimizer.set_state(&mut source.network.optimizer.state().into_iter());
+
+        self.network = network;
+        self.schedule = source.schedule.clone();
+
+        Ok(())
+    }
+
+    fn explore(hyper_params: &mut HyperParams, perturbation: f64, rng: &mut SeededRng) {
+        let mut perturb = |value: &mut f64| {
+            *value *= match rng.gen_bool(0.5) {
+                true => 1.0 - perturbation,
+                false => 1.0 + perturbation
+            };
+        };
+
+        let regularization = &mut hyper_params.regularization;
+
+        perturb(&mut hyper_params.learning_rate.alpha);
+        perturb(&mut regularization.dropout_rate.input_layer);
+        perturb(&mut regularization.dropout_rate.hidden_layer);
+        perturb(&mut regularization.elastic_net.weights.l1);
+        perturb(&mut regularization.elastic_net.weights.l2);
+
+        regularization.dropout_rate.input_layer = regularization.dropout_rate.input_layer.min(MAX_DROPOUT_RATE);
+        regularization.dropout_rate.hidden_layer = regularization.dropout_rate.hidden_layer.min(MAX_DROPOUT_RATE);
+    }
+}
+
+impl PopulationBasedTraining {
+    /// Returns the best member once all epochs are trained, the ranking after each interval is reported to
+    /// 'observer'. Fails if the population, the base or the sampled hyperparameters are invalid.
+    pub fn run(
+        &self,
+        base: &HyperParams,
+        train_data: &Data,
+        validation_data: &Data,
+        observer: &mut dyn Observer
+    ) -> Result<Member, ConfigError> {
+        self.validate()?;
+        self.validate_base(base)?;
+
+        // Members are reproducible if the base hyperparameters are seeded
+        let mut rng = SeededRng::seed_from_u64(base.seed.unwrap_or_else(rand::random));
+
+        let mut members = (0..self.population)
+            .map(|id| Ok(Member {
+                id,
+                network: Network::new(self.space.sample(base, &mut rng))?,
+                accuracy: 0.0,
+                cost: f64::INFINITY,
+                score: f64::NAN,
+                schedule: Vec::new()
+            }))
+            .collect::<Result<Vec<Member>, ConfigError>>()?;
+
+        let interval = self.interval.max(1);
+        let mut epoch = 1;
+
+        while epoch <= self.epochs {
+            let last_epoch = (epoch + interval - 1).min(self.epochs);
+
+            thread::scope(|scope| {
+                for member in members.iter_mut() {
+                    scope.spawn(move || member.train(epoch, last_epoch, self.metric, train_data, validation_data));
+                }
+            });
+
+            members.sort_by(|a, b| self.compare(a, b));
+
+            // Replace the worst members, the last interval is not followed by exploitation
+            let count = match last_epoch < self.epochs {
+                true => ((self.population as f64 * self.exploit_fraction).round() as usize).min(self.population / 2),
+                false => 0
+            };
+
+            let (best, rest) = members.split_at_mut(self.population - count);
+
+            for (member, source) in rest.iter_mut().zip(best.iter()) {
+                member.exploit(source, self.perturbation, &mut rng)?;
+            }
+
+            observer.population(last_epoch, &members, count);
+
+            epoch = last_epoch + 1;
+        }
+
+        Ok(members.swap_remove(0))
+    }
+
+    // Better members compare as less
+    fn compare(&self, a: &Member, b: &Member) -> Ordering {
+        self.direction.compare(a.score, b.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dataset::Normalization,
+        network::{ observer::Silent, optimizations::activations::ActivationType::*, search::Range }
+    };
+
+    fn data() -> Data {
+        Data {
+            inputs: (0..12).map(|sample| (0..4).map(|pixel| ((sample * 3 + pixel) % 5) as f64 / 4.0).collect()).collect(),
+            targets: (0..12).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    #[test]
+    fn diverged_members_are_never_the_best() {
+        let data = data();
+
+        let mut base = HyperParams::inference(vec![4, 8, 3], &[LeakyRelu, LeakyRelu], Normalization::GRAYSCALE);
+        base.batch_size = 2;
+        base.seed = Some(5);
+
+        for direction in [Direction::Minimize, Direction::Maximize] {
+            // Learning rates of up to 1e4 let some members diverge to NaN outputs and costs
+            let pbt = PopulationBasedTraining {
+                population: 6,
+                epochs: 4,
+                interval: 2,
+                exploit_fraction: 0.25,
+                perturbation: 0.2,
+                space: SearchSpace {
+                    alpha: Some(Range::LogUniform { min: 1e-3, max: 1e4 }),
+                    ..SearchSpace::default()
+                },
+                metric: Metric::Loss,
+                direction
+            };
+
+            assert!(pbt.run(&base, &data, &data, &mut Silent).unwrap().score.is_finite());
+        }
+    }
+}
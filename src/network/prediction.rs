@@ -0,0 +1,237 @@
+use crate::dataset::Normalization;
+use super::{ state::Network, model::Model };
+use std::{ fmt, cmp::Ordering };
+
+/// Errors of validating the inputs of a batch.
+#[derive(Debug)]
+pub enum PredictionError {
+    /// The number of pixel values differs from the input layer
+    WrongLength {
+        /// Index of the input in the batch
+        input: usize,
+        /// Neurons of the input layer
+        expected: usize,
+        /// Pixel values of the input
+        found: usize
+    },
+    /// A pixel value is NaN
+    NotANumber {
+        /// Index of the input in the batch
+        input: usize,
+        /// Index of the value in the input
+        pixel: usize
+    },
+    /// A pixel value lies outside of the normalization of the model
+    OutOfRange {
+        /// Index of the input in the batch
+        input: usize,
+        /// Index of the value in the input
+        pixel: usize,
+        /// Raw pixel value
+        value: f64,
+        /// Normalization of the model
+        normalization: Normalization
+    }
+}
+
+impl fmt::Display for PredictionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongLength { input, expected, found } =>
+                write!(f, "input {input} has {found} pixel values, the model expects {expected}"),
+            Self::NotANumber { input, pixel } =>
+                write!(f, "pixel {pixel} of input {input} is NaN"),
+            Self::OutOfRange { input, pixel, value, normalization } => write!(
+                f,
+                "pixel {pixel} of input {input} is {value}, outside the normalization range {} - {}",
+                normalization.min,
+                normalization.max
+            )
+        }
+    }
+}
+
+impl PredictionError {
+    /// Index of the rejected input in its batch.
+    pub fn input(&self) -> usize {
+        match self {
+            Self::WrongLength { input, .. } | Self::NotANumber { input, .. } | Self::OutOfRange { input, .. } => *input
+        }
+    }
+}
+
+/// Digit predicted for one image.
+#[derive(Clone, Debug)]
+pub struct Prediction {
+    /// Most probable digit
+    pub digit: usize,
+    /// Probability of each digit
+    pub probabilities: Vec<f64>
+}
+
+impl Prediction {
+    /// Prediction of the class probabilities of an output layer, e.g. returned by [`Model::predict`].
+    pub fn new(probabilities: Vec<f64>) -> Self {
+        Self { digit: Network::arg_max(&probabilities), probabilities }
+    }
+
+    /// Prediction of the raw outputs of a network, e.g. the last layer of [`Network::outputs`].
+    pub fn from_outputs(outputs: &[f64]) -> Self {
+        Self::new(probabilities(outputs))
+    }
+
+    /// The 'k' most probable digits with their probability, from most to least probable.
+    pub fn top(&self, k: usize) -> Vec<(usize, f64)> {
+        let mut ranking = self.probabilities.iter().copied().enumerate().collect::<Vec<(usize, f64)>>();
+
+        ranking.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        ranking.truncate(k);
+
+        ranking
+    }
+}
+
+/// Most probable digit of one input of a batch and the 'k' most probable digits.
+#[derive(Clone, Debug)]
+pub struct RankedPrediction {
+    /// Most probable digit
+    pub digit: usize,
+    /// Digits with their probability, from most to least probable
+    pub top: Vec<(usize, f64)>
+}
+
+/// Class probabilities of the output layer. The outputs are trained towards one-hot targets, so they are
+/// clipped at 0 and scaled to sum up to 1. If no output is positive the softmax keeps their order.
+pub(crate) fn probabilities(outputs: &[f64]) -> Vec<f64> {
+    let sum = outputs.iter().map(|&output| output.max(0.0)).sum::<f64>();
+
+    if sum > 0.0 {
+        return outputs.iter().map(|&output| output.max(0.0) / sum).collect();
+    }
+
+    let max = outputs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exponentials = outputs.iter().map(|&output| (output - max).exp()).collect::<Vec<f64>>();
+    let sum = exponentials.iter().sum::<f64>();
+
+    exponentials.iter().map(|exponential| exponential / sum).collect()
+}
+
+impl Network {
+    /// Predicts the digit of raw pixel values, e.g. grayscale values from 0 to 255 for a model
+    /// trained on MNIST. The values are scaled with the normalization saved with the model.
+    ///
+    /// Panics if the number of pixels does not match the input layer.
+    pub fn predict(&mut self, pixels: &[f64]) -> Prediction {
+        let normalization = self.hyper_params.normalization;
+
+        assert_eq!(
+            pixels.len(),
+            self.hyper_params.composition[0],
+            "ERROR: number of pixels does not match the input layer"
+        );
+
+        let inputs = pixels.iter().map(|&value| normalization.apply(value)).collect::<Vec<f64>>();
+        self.forward(&inputs);
+
+        Prediction::from_outputs(self.outputs.last().unwrap())
+    }
+}
+
+impl Model {
+    /// Checks that the raw pixel values of the input at 'index' of a batch fit the input layer and lie within
+    /// the normalization of the model.
+    pub fn validate(&self, index: usize, pixels: &[f64]) -> Result<(), PredictionError> {
+        let normalization = self.normalization();
+        let expected = self.composition()[0];
+
+        if pixels.len() != expected {
+            return Err(PredictionError::WrongLength { input: index, expected, found: pixels.len() });
+        }
+
+        for (pixel, &value) in pixels.iter().enumerate() {
+            if value.is_nan() {
+                return Err(PredictionError::NotANumber { input: index, pixel });
+            }
+
+            if !(0.0..=1.0).contains(&normalization.apply(value)) {
+                return Err(PredictionError::OutOfRange { input: index, pixel, value, normalization });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Predicts a batch of raw pixel values and returns the 'k' most probable digits of each input.
+    /// The whole batch is validated before anything is predicted.
+    pub fn predict_batch(&self, inputs: &[Vec<f64>], k: usize) -> Result<Vec<RankedPrediction>, PredictionError> {
+        for (index, pixels) in inputs.iter().enumerate() {
+            self.validate(index, pixels)?;
+        }
+
+        Ok(inputs.iter()
+            .map(|pixels| {
+                let prediction = Prediction::new(self.predict(pixels));
+
+                RankedPrediction { digit: prediction.digit, top: prediction.top(k) }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ state::HyperParams, optimizations::activations::ActivationType::* };
+
+    fn model() -> Model {
+        let mut hyper_params = HyperParams::inference(vec![4, 5, 3], &[Tanh, LeakyRelu], Normalization::GRAYSCALE);
+        hyper_params.seed = Some(29);
+
+        Model::from(&Network::new(hyper_params).unwrap())
+    }
+
+    #[test]
+    fn rejects_invalid_inputs() {
+        let model = model();
+        let valid = vec![0.0, 64.0, 128.0, 255.0];
+
+        let error = model.predict_batch(&[valid.clone(), vec![0.0; 3]], 2).unwrap_err();
+        assert!(matches!(error, PredictionError::WrongLength { input: 1, expected: 4, found: 3 }));
+
+        let error = model.predict_batch(&[vec![0.0, 1.0, f64::NAN, 2.0], valid.clone()], 2).unwrap_err();
+        assert!(matches!(error, PredictionError::NotANumber { input: 0, pixel: 2 }));
+
+        for value in [-1.0, 256.0, f64::INFINITY] {
+            let error = model.predict_batch(&[valid.clone(), valid.clone(), vec![0.0, value, 0.0, 0.0]], 2).unwrap_err();
+
+            assert!(matches!(error, PredictionError::OutOfRange { input: 2, pixel: 1, .. }));
+            assert_eq!(error.input(), 2);
+        }
+    }
+
+    #[test]
+    fn ranks_the_most_probable_digits() {
+        let model = model();
+        let inputs = vec![vec![0.0, 64.0, 128.0, 255.0], vec![255.0, 0.0, 30.0, 90.0]];
+
+        for (pixels, prediction) in inputs.iter().zip(model.predict_batch(&inputs, 2).unwrap()) {
+            let probabilities = model.predict(pixels);
+
+            assert_eq!(prediction.top.len(), 2);
+            assert_eq!(prediction.top[0], (prediction.digit, probabilities[prediction.digit]));
+            assert!(prediction.top[0].1 >= prediction.top[1].1);
+            assert!(probabilities.iter().all(|&probability| probability <= prediction.top[0].1));
+        }
+    }
+
+    #[test]
+    fn probabilities_sum_to_one() {
+        // Positive outputs are scaled, all negative outputs fall back to the softmax
+        for (outputs, digit) in [([0.2, -0.1, 0.6], 2), ([-0.5, -0.2, -0.9], 1)] {
+            let prediction = Prediction::from_outputs(&outputs);
+
+            assert!((prediction.probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert_eq!(prediction.digit, digit);
+        }
+    }
+}
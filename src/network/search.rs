@@ -0,0 +1,409 @@
+use super::{
+    optimizations::{
+        activations::{ Activation, ActivationType },
+        early_stopping::{ EarlyStopping, Metric, Direction },
+        learning_rate::LearningRate
+    },
+    state::{ Network, HyperParams, SeededRng },
+    observer::Observer,
+    config::ConfigError
+};
+use crate::dataset::Data;
+
+use rand::{ Rng, SeedableRng, seq::SliceRandom };
+use serde::{ Serialize, Deserialize };
+use std::{ fs::File, io::{ self, BufWriter, Write }, cmp::Ordering };
+
+/// Interval a hyperparameter is sampled from, both bounds are included.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum Range {
+    /// Uniform in the value
+    Uniform {
+        /// Lower bound
+        min: f64,
+        /// Upper bound
+        max: f64
+    },
+    /// Uniform in the logarithm of the value, suited for learning rates and regularization strengths
+    LogUniform {
+        /// Positive lower bound
+        min: f64,
+        /// Upper bound
+        max: f64
+    }
+}
+
+impl Range {
+    fn sample(&self, rng: &mut SeededRng) -> f64 {
+        match *self {
+            Self::Uniform { min, max } => rng.gen_range(min..=max),
+            Self::LogUniform { min, max } => rng.gen_range(min.ln()..=max.ln()).exp()
+        }
+    }
+}
+
+/// Hyperparameters to sample, fields set to 'None' keep the value of the base hyperparameters.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchSpace {
+    /// Sizes of the hidden layers, input and output layers are kept
+    pub hidden_layers: Option<Vec<Vec<usize>>>,
+    /// Activation function of all hidden layers, the output activation is kept
+    pub hidden_activation: Option<Vec<ActivationType>>,
+    /// Learning rate
+    pub alpha: Option<Range>,
+    /// Dropout rate of the input layer
+    pub input_dropout: Option<Range>,
+    /// Dropout rate of the hidden layers
+    pub hidden_dropout: Option<Range>,
+    /// L1 regularization of the weights
+    pub l1: Option<Range>,
+    /// L2 regularization of the weights
+    pub l2: Option<Range>,
+    /// Samples of each mini-batch step
+    pub batch_size: Option<Vec<usize>>
+}
+
+impl SearchSpace {
+    // Each sample gets its own seed drawn from 'rng'
+    pub(crate) fn sample(&self, base: &HyperParams, rng: &mut SeededRng) -> HyperParams {
+        let mut hyper_params = base.clone();
+        hyper_params.seed = Some(rng.gen());
+
+        if let Some(hidden_layers) = &self.hidden_layers {
+            let hidden_layers = hidden_layers.choose(rng).expect("ERROR: no hidden layer sizes to choose from");
+            let composition = &base.composition;
+
+            hyper_params.composition = [&composition[..1], hidden_layers, &composition[composition.len() - 1..]].concat();
+        }
+
+        // The number of activations follows the number of layers
+        let output_activation = base.activations.last().unwrap().clone();
+        let hidden_layer_count = hyper_params.composition.len() - 2;
+
+        let mut activations = match &self.hidden_activation {
+            Some(hidden_activation) => {
+                let hidden_activation = *hidden_activation.choose(rng).expect("ERROR: no activations to choose from");
+                Activation::get(&vec![hidden_activation; hidden_layer_count])
+            },
+            None => vec![base.activations[0].clone(); hidden_layer_count]
+        };
+
+        activations.push(output_activation);
+        hyper_params.activations = activations;
+
+        if let Some(alpha) = &self.alpha {
+            hyper_params.learning_rate.alpha = alpha.sample(rng);
+        }
+
+        if let Some(input_dropout) = &self.input_dropout {
+            hyper_params.regularization.dropout_rate.input_layer = input_dropout.sample(rng);
+        }
+
+        if let Some(hidden_dropout) = &self.hidden_dropout {
+            hyper_params.regularization.dropout_rate.hidden_layer = hidden_dropout.sample(rng);
+        }
+
+        if let Some(l1) = &self.l1 {
+            hyper_params.regularization.elastic_net.weights.l1 = l1.sample(rng);
+        }
+
+        if let Some(l2) = &self.l2 {
+            hyper_params.regularization.elastic_net.weights.l2 = l2.sample(rng);
+        }
+
+        if let Some(batch_size) = &self.batch_size {
+            hyper_params.batch_size = *batch_size.choose(rng).expect("ERROR: no batch sizes to choose from");
+        }
+
+        // Per-layer max norm limits depend on the number of layers
+        if hyper_params.composition.len() != base.composition.len() {
+            hyper_params.regularization.max_norm_constraint.layer_limits = None;
+        }
+
+        hyper_params
+    }
+}
+
+/// How the sampled configurations are trained and compared.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum SearchStrategy {
+    /// Train each sampled configuration for at most 'epochs'
+    Random {
+        /// Number of sampled configurations
+        trials: usize,
+        /// Epochs of each trial
+        epochs: u32
+    },
+    /// Successive halving over several brackets, poor trials are terminated after few epochs
+    Hyperband {
+        /// Epochs of the trials that are never terminated
+        max_epochs: u32,
+        /// Each rung keeps one in 'reduction_factor' trials
+        reduction_factor: u32
+    }
+}
+
+/// Hyperparameter search around base hyperparameters, read from a search config file by [`Search::load`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Search {
+    /// Sampled hyperparameters, all others are kept
+    #[serde(default)]
+    pub space: SearchSpace,
+    /// Number of trials and their epochs
+    pub strategy: SearchStrategy,
+    /// Validation metric used to rank the trials
+    pub metric: Metric,
+    /// Whether lower or higher scores are better
+    pub direction: Direction
+}
+
+/// One sampled configuration and its validation results.
+pub struct Trial {
+    /// Number of the trial in the order of sampling
+    pub id: usize,
+    /// Sampled hyperparameters
+    pub hyper_params: HyperParams,
+    /// Trained epochs
+    pub epochs: u32,
+    /// Validation accuracy after the last epoch
+    pub accuracy: f64,
+    /// Validation cost after the last epoch
+    pub cost: f64,
+    /// Value of the metric of the search
+    pub score: f64,
+    /// Set once the early stopping criterion of the trial is met
+    pub stopped: bool
+}
+
+/// Trials of a search ranked from best to worst.
+pub struct SearchResults {
+    /// At least one trial, the best one first
+    pub trials: Vec<Trial>
+}
+
+impl SearchResults {
+    /// Hyperparameters of the best trial.
+    pub fn best(&self) -> &HyperParams {
+        &self.trials[0].hyper_params
+    }
+
+    /// Writes one CSV row for each trial in the order of the ranking.
+    pub fn save(&self, file_name: &str) -> io::Result<()> {
+        let file = File::create(file_name)?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "rank,trial,score,accuracy,cost,epochs,composition,alpha,input_dropout,hidden_dropout,l1,l2,batch_size")?;
+
+        for (rank, trial) in self.trials.iter().enumerate() {
+            let HyperParams { composition, regularization, learning_rate, batch_size, .. } = &trial.hyper_params;
+
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                rank + 1,
+                trial.id,
+                trial.score,
+                trial.accuracy,
+                trial.cost,
+                trial.epochs,
+                composition.iter().map(usize::to_string).collect::<Vec<String>>().join("-"),
+                learning_rate.alpha,
+                regularization.dropout_rate.input_layer,
+                regularization.dropout_rate.hidden_layer,
+                regularization.elastic_net.weights.l1,
+                regularization.elastic_net.weights.l2,
+                batch_size
+            )?;
+        }
+
+        writer.flush()
+    }
+}
+
+impl Search {
+    /// Trains the sampled trials on 'train_data' and ranks them on 'validation_data', each finished trial
+    /// is reported to 'observer'. Fails if the search, the base or the sampled hyperparameters are invalid.
+    pub fn run(
+        &self,
+        base: &HyperParams,
+        train_data: &Data,
+        validation_data: &Data,
+        observer: &mut dyn Observer
+    ) -> Result<SearchResults, ConfigError> {
+        self.validate()?;
+        self.validate_base(base)?;
+
+        // Trials are reproducible if the base hyperparameters are seeded
+        let mut rng = SeededRng::seed_from_u64(base.seed.unwrap_or_else(rand::random));
+
+        let mut trials = match self.strategy {
+            SearchStrategy::Random { trials, epochs } => (0..trials)
+                .map(|id| {
+                    let (mut trial, mut network) = self.create_trial(id, base, &mut rng)?;
+                    self.train_trial(&mut trial, &mut network, epochs, train_data, validation_data, observer);
+
+                    Ok(trial)
+                })
+                .collect::<Result<Vec<Trial>, ConfigError>>()?,
+            SearchStrategy::Hyperband { max_epochs, reduction_factor } => {
+                self.hyperband(base, max_epochs, reduction_factor, train_data, validation_data, &mut rng, observer)?
+            }
+        };
+
+        trials.sort_by(|a, b| self.compare(a, b));
+
+        Ok(SearchResults { trials })
+    }
+
+    // Better trials compare as less
+    fn compare(&self, a: &Trial, b: &Trial) -> Ordering {
+        self.direction.compare(a.score, b.score)
+    }
+
+    fn create_trial(&self, id: usize, base: &HyperParams, rng: &mut SeededRng) -> Result<(Trial, Network), ConfigError> {
+        let hyper_params = self.space.sample(base, rng);
+
+        let trial = Trial {
+            id,
+            hyper_params: hyper_params.clone(),
+            epochs: 0,
+            accuracy: 0.0,
+            cost: f64::INFINITY,
+            score: f64::NAN,
+            stopped: false
+        };
+
+        Ok((trial, Network::new(hyper_params)?))
+    }
+
+    // Continue training the trial until it has been trained for 'epochs' in total
+    fn train_trial(
+        &self,
+        trial: &mut Trial,
+        network: &mut Network,
+        epochs: u32,
+        train_data: &Data,
+        validation_data: &Data,
+        observer: &mut dyn Observer
+    ) {
+        while trial.epochs < epochs && !trial.stopped {
+            trial.epochs += 1;
+            network.train_epoch(train_data, trial.epochs);
+
+            let (accuracy, cost) = network.test(validation_data);
+            trial.stopped = EarlyStopping::check(network, trial.epochs, accuracy, cost, validation_data);
+
+            LearningRate::end_epoch(network, trial.epochs, cost);
+
+            trial.accuracy = accuracy;
+            trial.cost = cost;
+        }
+
+        trial.score = self.metric.score(network, trial.accuracy, trial.cost, validation_data);
+
+        observer.trial(trial);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn hyperband(
+        &self,
+        base: &HyperParams,
+        max_epochs: u32,
+        reduction_factor: u32,
+        train_data: &Data,
+        validation_data: &Data,
+        rng: &mut SeededRng,
+        observer: &mut dyn Observer
+    ) -> Result<Vec<Trial>, ConfigError> {
+        let eta = reduction_factor.max(2) as f64;
+        let max_epochs = max_epochs.max(1) as f64;
+
+        // Number of brackets, each one trades the number of trials against the epochs per trial
+        let s_max = (max_epochs.ln() / eta.ln() + 1e-9).floor() as i32;
+
+        let mut finished = Vec::new();
+        let mut id = 0;
+
+        for s in (0..=s_max).rev() {
+            let count = ((s_max + 1) as f64 / (s + 1) as f64 * eta.powi(s)).ceil() as usize;
+            let epochs = max_epochs * eta.powi(-s);
+
+            let mut bracket = (id..id + count)
+                .map(|id| self.create_trial(id, base, rng))
+                .collect::<Result<Vec<(Trial, Network)>, ConfigError>>()?;
+
+            id += count;
+
+            for rung in 0..=s {
+                let rung_epochs = (epochs * eta.powi(rung)).round().max(1.0) as u32;
+
+                for (trial, network) in bracket.iter_mut() {
+                    self.train_trial(trial, network, rung_epochs, train_data, validation_data, observer);
+                }
+
+                bracket.sort_by(|(a, _), (b, _)| self.compare(a, b));
+
+                // Keep the best fraction of the trials for the next rung
+                let survivors = match rung == s {
+                    true => 0,
+                    false => ((bracket.len() as f64 / eta).floor() as usize).max(1)
+                };
+
+                finished.extend(bracket.drain(survivors..).map(|(trial, _)| trial));
+            }
+        }
+
+        Ok(finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ dataset::Normalization, network::{ observer::Silent, optimizations::activations::ActivationType::* } };
+
+    fn data() -> Data {
+        Data {
+            inputs: (0..12).map(|sample| (0..4).map(|pixel| ((sample * 3 + pixel) % 5) as f64 / 4.0).collect()).collect(),
+            targets: (0..12).map(|sample| (0..3).map(|class| (sample % 3 == class) as u8 as f64).collect()).collect()
+        }
+    }
+
+    fn base() -> HyperParams {
+        let mut base = HyperParams::inference(vec![4, 8, 3], &[LeakyRelu, LeakyRelu], Normalization::GRAYSCALE);
+
+        base.learning_rate.alpha = 0.05;
+        base.batch_size = 2;
+        base.seed = Some(5);
+
+        base
+    }
+
+    #[test]
+    fn diverged_trials_rank_last() {
+        let data = data();
+
+        for direction in [Direction::Minimize, Direction::Maximize] {
+            // Learning rates of up to 1e12 let some trials diverge to NaN outputs and costs
+            let search = Search {
+                space: SearchSpace {
+                    alpha: Some(Range::LogUniform { min: 1e-3, max: 1e12 }),
+                    ..SearchSpace::default()
+                },
+                strategy: SearchStrategy::Random { trials: 6, epochs: 3 },
+                metric: Metric::Loss,
+                direction
+            };
+
+            let results = search.run(&base(), &data, &data, &mut Silent).unwrap();
+            let finite = results.trials.iter().take_while(|trial| trial.score.is_finite()).count();
+
+            assert!(finite > 0 && finite < results.trials.len());
+            assert!(results.trials[finite..].iter().all(|trial| !trial.score.is_finite()));
+        }
+    }
+}
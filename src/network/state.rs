@@ -1,123 +1,143 @@
-#![allow(dead_code)]
-
-use crate::network::utils;
+use crate::{ dataset::Normalization, network::{ utils, checkpoint::Checkpoint, config::ConfigError } };
 use super::optimizations::{ 
     activations::Activation,
     regularization::{ Regularization, Dropout },
-    learning_rate::LearningRate,
-    adam::{ AdamHyperParams, Adam },
-    early_stopping::EarlyStopping,
+    learning_rate::{ LearningRate, Scheduler },
+    optimizer::{ OptimizerType, Optimizer, Parameters },
+    early_stopping::{ EarlyStopping, BestParameters },
+    gradient_clipping::GradientClipping,
+    lbfgs::LbfgsHyperParams,
+    lookahead::LookaheadHyperParams,
+    weight_averaging::{ WeightAveraging, WeightAverages },
     batch::Batch
 };
 
-use std::{ fs::File, io::{ BufWriter, Write, BufReader, BufRead} };
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{ Serialize, Deserialize };
 
 pub type Vec2D = Vec<Vec<f64>>;
 
+// Source of all randomness of a network, seeded for reproducible runs
+pub type SeededRng = ChaCha8Rng;
+
+/// How the parameters are optimized.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum TrainingMode {
+    /// Epochs of mini-batch steps taken by 'optimizer'
+    MiniBatch,
+    /// Full-batch quasi-Newton optimization, ignores 'optimizer', 'learning_rate' and 'batch_size'
+    Lbfgs(LbfgsHyperParams)
+}
+
+/// Composition of the network and configuration of every training feature, loaded from and saved to
+/// TOML or JSON config files by [`HyperParams::load`] and [`HyperParams::save`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct HyperParams {
+    /// Number of neurons in each layer, from the input to the output layer
     pub composition: Vec<usize>,
+    /// Activation function of each hidden and output layer
     pub activations: Vec<Activation>,
+    /// Regularization against overfitting
     pub regularization: Regularization,
+    /// Learning rate and its schedule
     pub learning_rate: LearningRate,
-    pub optimizer: AdamHyperParams,
+    /// Optimizer of the weights and biases
+    pub optimizer: OptimizerType,
+    /// Wraps 'optimizer' if set
+    pub lookahead: Option<LookaheadHyperParams>,
+    /// Limits of the gradients
+    pub gradient_clipping: GradientClipping,
+    /// Samples of each mini-batch step
     pub batch_size: usize,
-    pub early_stopping: EarlyStopping
+    /// When to stop training
+    pub early_stopping: EarlyStopping,
+    /// Averages of the parameters recorded while training
+    pub weight_averaging: WeightAveraging,
+    /// Mini-batch or full-batch training
+    pub training_mode: TrainingMode,
+    /// Saves the complete training state periodically if set
+    pub checkpoint: Option<Checkpoint>,
+    /// Scaling of the raw inputs, saved with the model, grayscale values if missing in a config file
+    #[serde(default)]
+    pub normalization: Normalization,
+    /// Seed of the network's random number generator, drawn from entropy if 'None'
+    pub seed: Option<u64>
 }
 
+/// Fully connected neural network together with its training state.
 pub struct Network {
+    /// Incoming weights of each neuron, one matrix for each hidden and output layer
     pub weights: Vec<Vec2D>,
+    /// Bias of each neuron of the hidden and output layers
     pub biases: Vec2D,
-    pub net_inputs: Vec2D,
+    pub(crate) inputs: Vec<f64>, // Inputs of the last forward pass after input-layer dropout
+    pub(crate) net_inputs: Vec2D,
+    /// Activations of each hidden and output layer after the last [`Network::forward`]
     pub outputs: Vec2D,
-    pub costs: Vec2D,
-    pub optimizer: Adam,
-    pub dropout_mask: Vec2D, // Mask used to drop out certain neurons
-    pub batch: Batch,
-    pub performance: Vec<f64>, // Track network's accuracy, used for early stopping
+    pub(crate) costs: Vec2D,
+    pub(crate) gradients: Parameters,
+    pub(crate) optimizer: Box<dyn Optimizer>,
+    pub(crate) scheduler: Box<dyn Scheduler>,
+    pub(crate) input_dropout_mask: Vec<f64>, // Mask used to drop out certain inputs
+    pub(crate) dropout_mask: Vec2D, // Mask used to drop out certain neurons of the hidden layers
+    pub(crate) batch: Batch,
+    pub(crate) averages: WeightAverages,
+    pub(crate) performance: Vec<f64>, // Track network's accuracy, used for early stopping
+    pub(crate) best: Option<BestParameters>, // Parameters of the best epoch, used for best-model early stopping
+    /// Number of completed epochs
+    pub epoch: u32,
+    /// Seed of the random number generator, drawn from entropy unless set in the hyperparameters
+    pub seed: u64,
+    pub(crate) rng: SeededRng,
+    /// Hyperparameters the network was created with, 'learning_rate.alpha' follows the scheduler
     pub hyper_params: HyperParams,
 }
 
 impl Network {   
-    pub fn new(hyper_params: HyperParams) -> Self {
-        let composition = &hyper_params.composition;
+    /// Randomly initialized network, fails if the hyperparameters are invalid.
+    pub fn new(hyper_params: HyperParams) -> Result<Self, ConfigError> {
+        hyper_params.validate()?;
 
-        assert_eq!(
-            composition.len() - 1,
-            hyper_params.activations.len(), 
-            "ERROR: wrong number of activation functions"
-        );
+        let composition = &hyper_params.composition;
 
         let zeros_2d_vec = utils::zeros_2d_vec(composition, 1);
-        let random_3d_vec = utils::random_3d_vec(&mut rand::thread_rng(), composition);
+        let seed = hyper_params.seed.unwrap_or_else(rand::random);
+        let mut rng = SeededRng::seed_from_u64(seed);
+
+        let random_3d_vec = utils::random_3d_vec(&mut rng, composition);
+        let (input_dropout_mask, dropout_mask) = Dropout::init_masks(composition);
 
-        Self {
+        Ok(Self {
             weights: random_3d_vec,
             biases: zeros_2d_vec.clone(),
+            inputs: vec![0.0; composition[0]],
             net_inputs: zeros_2d_vec.clone(),
             outputs: zeros_2d_vec.clone(),
             costs: zeros_2d_vec.clone(),
-            optimizer: Adam::new(composition),
-            dropout_mask: Dropout::init_mask(composition),
+            gradients: Parameters::new(composition),
+            optimizer: hyper_params.optimizer.get(hyper_params.lookahead, composition),
+            scheduler: hyper_params.learning_rate.scheduler.get(hyper_params.learning_rate.alpha),
+            input_dropout_mask,
+            dropout_mask,
             batch: Batch::new(composition),
+            averages: WeightAverages::new(),
             performance: Vec::new(),
+            best: None,
+            epoch: 0,
+            seed,
+            rng,
             hyper_params,
-        }
+        })
     }
-
-    pub fn save(&self) {        
-        let file = File::create("parameters.txt").expect("ERROR: opening file");
-        let mut writer = BufWriter::new(file);
-
-        let mut write = |vec: &[f64]| {
-            let vec: Vec<String> = vec.iter().map(f64::to_string).collect();
-
-            for num in &vec {
-                writeln!(writer, "{num}").expect("ERROR: writing into buffer");
-            }
-        };
-
-        for biases in &self.biases {
-            write(biases);
-        }
-
-        for weights in self.weights.iter().flat_map(|weights| weights.iter()) {
-            write(weights);
-        }
-
-        writer.flush().expect("ERROR: flushing file buffer");
+    /// Number of optimizer steps taken so far.
+    pub fn iteration(&self) -> i32 {
+        self.optimizer.iteration()
     }
 
-    pub fn load(hyper_params: HyperParams) -> Self {
-        let file = File::open("parameters.txt").expect("ERROR: opening file");
-        let reader = BufReader::new(file);
-
-        let params: Vec<f64> = reader.lines()
-            .map(|line| {
-                line.expect("ERROR: reading line")
-                    .trim_matches('"')
-                    .parse::<f64>().expect("ERROR: parsing f64")
-            })
-            .collect();
-
-        let mut network = Self::new(hyper_params);
-        let mut index = 0;
-
-        for biases in &mut network.biases {
-            for bias in &mut *biases {
-                *bias = params[index];
-                index += 1;
-            }
-        }
-
-        for weights in &mut network.weights {
-            for weights in &mut *weights {
-                for weight in &mut *weights {
-                    *weight = params[index];
-                    index += 1;
-                }
-            }
-        }
-
-        network
+    /// Epoch and validation score of the best epoch so far, only tracked by best-model early stopping.
+    pub fn best_epoch(&self) -> Option<(u32, f64)> {
+        self.best.as_ref().map(|best| (best.epoch, best.score))
     }
 }
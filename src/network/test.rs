@@ -2,6 +2,13 @@ use crate::dataset::Data;
 use super::state::Network;
 
 impl Network {    
+    pub(crate) fn compute_cost(outputs: &[f64], targets: &[f64]) -> f64 {
+        outputs.iter().zip(targets)
+            .map(|(output, target)| 0.5 * (target - output).powi(2))
+            .sum()
+    }
+
+    /// Returns the accuracy and the average cost including the regularization penalty.
     pub fn test(&mut self, data: &Data) -> (f64, f64) {
         let mut correct_count = 0.0;
         let mut cost = 0.0;
@@ -9,25 +16,60 @@ impl Network {
         for (input, target) in data.inputs.iter().zip(data.targets.iter()) {
             self.forward(input);
 
-            let predicted_output_index = self.outputs.last().unwrap()
-                .iter()
-                .enumerate()
-                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                .map(|(index, _)| index)
-                .unwrap();
+            let predicted_output_index = Self::arg_max(self.outputs.last().unwrap());
 
-            if Data::one_hot_encode(predicted_output_index) == *target {
+            if Data::one_hot_encode(predicted_output_index, target.len()) == *target {
                 correct_count += 1.0
             }
 
-            for (output, target) in self.outputs.last().unwrap().iter().zip(target) {
-                cost += 0.5 * (*target - *output).powi(2);
-            }
+            cost += Self::compute_cost(self.outputs.last().unwrap(), target);
         }
 
         let accuracy = correct_count / data.targets.len() as f64;
-        let cost = cost / data.inputs.len() as f64;        
+        let cost = cost / data.inputs.len() as f64 + self.compute_regularization_penalty();
 
         (accuracy, cost)
     }
+
+    /// Macro-averaged F1 score, classes without any predictions or targets count as 0.
+    pub fn compute_f1_score(&mut self, data: &Data) -> f64 {
+        let classes = data.targets[0].len();
+
+        let mut true_positives = vec![0.0; classes];
+        let mut predicted = vec![0.0; classes];
+        let mut actual = vec![0.0; classes];
+
+        for (input, target) in data.inputs.iter().zip(data.targets.iter()) {
+            self.forward(input);
+
+            let prediction = Self::arg_max(self.outputs.last().unwrap());
+            let target = Self::arg_max(target);
+
+            predicted[prediction] += 1.0;
+            actual[target] += 1.0;
+
+            if prediction == target {
+                true_positives[prediction] += 1.0;
+            }
+        }
+
+        let f1_sum: f64 = (0..classes)
+            .map(|class| match predicted[class] + actual[class] {
+                0.0 => 0.0,
+                total => 2.0 * true_positives[class] / total
+            })
+            .sum();
+
+        f1_sum / classes as f64
+    }
+
+    /// Index of the largest value, e.g. the digit of a one-hot target or of the outputs.
+    /// NaN values, e.g. outputs of a diverged network, do not panic.
+    pub fn arg_max(values: &[f64]) -> usize {
+        values.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+            .unwrap()
+    }
 }
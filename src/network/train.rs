@@ -1,54 +1,104 @@
 use super::{ 
-    optimizations::{ regularization::Dropout, batch::Batch, early_stopping::EarlyStopping, learning_rate::LearningRate }, 
-    state::Network 
+    optimizations::{ 
+        regularization::Dropout, batch::Batch, early_stopping::EarlyStopping, learning_rate::LearningRate,
+        gradient_clipping::GradientClipping, weight_averaging::WeightAverages
+    }, 
+    state::{ Network, TrainingMode },
+    checkpoint::Checkpoint,
+    observer::{ Observer, EpochReport }
 };
-use crate::monitor::monitor_training;
 use crate::dataset::Data;
 
 use std::time::{ Instant, Duration };
 
 impl Network {
-    pub fn train(&mut self, train_data: &Data, validation_data: &Data) {       
+    /// Trains until the early stopping criterion is met, evaluating every epoch on 'validation_data'.
+    /// The results of each epoch are reported to 'observer'.
+    pub fn train(&mut self, train_data: &Data, validation_data: &Data, observer: &mut dyn Observer) {       
+        if let TrainingMode::Lbfgs(hyper_params) = self.hyper_params.training_mode {
+            return self.train_lbfgs(train_data, validation_data, hyper_params, observer);
+        }
+
         let mut duration = Duration::ZERO;
-        let mut epoch = 0;
         
+        // Continues from the epoch of a resumed checkpoint
         loop {
             let timestamp = Instant::now();
-            epoch += 1;
-
-            for (inputs, targets) in train_data.inputs.chunks(self.hyper_params.batch_size)
-                .zip(train_data.targets.chunks(self.hyper_params.batch_size))
-            {                
-                Dropout::generate_mask(self);
-
-                for (inputs, targets) in inputs.iter()
-                    .zip(targets.iter())
-                {                
-                    self.optimizer.iteration += 1;
-
-                    self.forward(inputs);
-                    self.backward(inputs, targets);             
-                }                
-                
-                Batch::update(self, inputs.len() as f64);
-            }
+            let epoch = self.epoch + 1;
 
-            Dropout::set_all_active_mask(self);
+            let clip_count = self.train_epoch(train_data, epoch);
             
             duration += timestamp.elapsed();
 
             let (accuracy, cost) = self.test(validation_data);
-            let early_stop = EarlyStopping::check(self, accuracy);
+            let early_stop = EarlyStopping::check(self, epoch, accuracy, cost, validation_data);
 
-            monitor_training(
-                epoch, self.hyper_params.learning_rate.alpha, accuracy, cost, duration, early_stop
-            );
+            let clipped = match self.hyper_params.gradient_clipping.is_enabled() {
+                true => Some(clip_count),
+                false => None
+            };
+
+            observer.epoch(&EpochReport {
+                epoch, 
+                learning_rate: self.hyper_params.learning_rate.alpha, 
+                accuracy, 
+                cost, 
+                regularization_penalty: self.compute_regularization_penalty(), 
+                clipped, 
+                duration, 
+                stopped: early_stop
+            });
 
             if early_stop {
+                EarlyStopping::restore_best(self);
+
+                if let Some(averaging) = self.hyper_params.weight_averaging.apply {
+                    self.apply_averaged_parameters(averaging);
+                }
+
                 break;
             }
             
-            LearningRate::update(self, &epoch);
+            LearningRate::end_epoch(self, epoch, cost);
+            Checkpoint::update(self, observer);
+        }
+    }
+
+    // One pass of mini-batch steps over the training data, returns the number of steps with clipped gradients
+    pub(crate) fn train_epoch(&mut self, train_data: &Data, epoch: u32) -> u32 {
+        let mut clip_count = 0;
+        let batches = train_data.inputs.len().div_ceil(self.hyper_params.batch_size);
+
+        for (batch, (inputs, targets)) in train_data.inputs.chunks(self.hyper_params.batch_size)
+            .zip(train_data.targets.chunks(self.hyper_params.batch_size))
+            .enumerate()
+        {                
+            LearningRate::update(self, epoch, batch, batches);
+            Dropout::generate_mask(self);
+
+            for (inputs, targets) in inputs.iter()
+                .zip(targets.iter())
+            {
+                self.forward(inputs);
+                self.backward(targets);
+                Batch::accumulate(self);
+            }                
+            
+            // One optimizer step per batch with the mean gradient of its samples
+            Batch::average(self, inputs.len() as f64);
+
+            if GradientClipping::clip(self) {
+                clip_count += 1;
+            }
+
+            self.step();
         }
+
+        Dropout::set_all_active_mask(self);
+        WeightAverages::update_swa(self, epoch);
+
+        self.epoch = epoch;
+
+        clip_count
     }
 }
@@ -1,7 +1,7 @@
 use super::state::Vec2D;
-use rand::{ Rng, rngs::ThreadRng };
+use rand::Rng;
 
-pub fn random_3d_vec(rng: &mut ThreadRng, composition: &[usize]) -> Vec<Vec2D> {
+pub fn random_3d_vec(rng: &mut impl Rng, composition: &[usize]) -> Vec<Vec2D> {
     let mut random_3d_vec = Vec::with_capacity(composition.len() - 1);
     
     for index in 1..composition.len() {
@@ -41,3 +41,11 @@ pub fn zeros_3d_vec(composition: &[usize]) -> Vec<Vec2D> {
     zeros_3d_vec
 }
 
+
+// Iterate over all weights followed by all biases
+pub fn flatten<'a>(weights: &'a [Vec2D], biases: &'a [Vec<f64>]) -> impl Iterator<Item = &'a f64> {
+    weights.iter()
+        .flat_map(|layer| layer.iter())
+        .flat_map(|neuron| neuron.iter())
+        .chain(biases.iter().flat_map(|layer| layer.iter()))
+}
@@ -0,0 +1,323 @@
+use digit_recognition::{ Image, Model, RankedPrediction };
+
+use crate::monitor;
+
+use serde::Deserialize;
+use serde_json::{ json, Value };
+use std::{
+    io::{ self, BufRead, BufReader, Read, Write },
+    net::{ TcpListener, TcpStream },
+    panic::{ self, AssertUnwindSafe },
+    sync::{ Arc, Mutex, mpsc },
+    thread,
+    time::{ Duration, Instant }
+};
+
+// Requests with a larger header are rejected, the body size is limited by 'ServerConfig'
+const MAX_HEADER_SIZE: u64 = 8 * 1024;
+// Time for reading a complete request and for writing the response, so slow clients that send or receive
+// a few bytes at a time can not block a worker
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of 'serve', the server only listens on the local host.
+pub struct ServerConfig {
+    pub port: u16,
+    // Number of threads handling connections, each connection is handled by a single thread
+    pub workers: usize,
+    // Maximum size of a request body in bytes
+    pub max_body_size: usize,
+    // Number of most probable digits if a request does not ask for a number with '?top=<k>'
+    pub top: usize
+}
+
+// Error response with its status code
+struct HttpError {
+    status: u16,
+    message: String
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+// Reads from the stream until a deadline for the whole request, unlike the read timeout of a single read
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buffer)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Option<String>,
+    content_type: Option<String>,
+    body: Vec<u8>
+}
+
+// Body of a JSON request, a single input or a batch of inputs
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PredictRequest {
+    pixels: Pixels
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Pixels {
+    Single(Vec<f64>),
+    Batch(Vec<Vec<f64>>)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error"
+    }
+}
+
+/// Most probable digit and the ranking of a prediction, also used by the JSON output of 'predict'.
+pub fn prediction_json(prediction: &RankedPrediction) -> Value {
+    json!({
+        "digit": prediction.digit,
+        "top": prediction.top.iter()
+            .map(|&(digit, probability)| json!({ "digit": digit, "probability": probability }))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Listens on 'localhost:<port>' until the process is stopped.
+///
+/// `GET /health` returns the status and the composition of the model, `POST /predict` the top-k predictions
+/// of a JSON body '{"pixels": [...]}' with one or more inputs, or of a PGM, PPM or BMP image.
+pub fn serve(model: Model, config: &ServerConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", config.port))?;
+
+    let model = Arc::new(model);
+    let (sender, receiver) = mpsc::channel::<TcpStream>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for _ in 0..config.workers {
+        let (model, receiver) = (Arc::clone(&model), Arc::clone(&receiver));
+        let (max_body_size, top) = (config.max_body_size, config.top);
+
+        thread::spawn(move || loop {
+            // The lock is released before the connection is handled
+            let stream = receiver.lock().expect("ERROR: connection queue is poisoned").recv();
+
+            match stream {
+                // Panics while responding are answered with 500 by 'handle', a panic while reading or writing
+                // only drops its connection
+                Ok(stream) => {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| handle(stream, &model, max_body_size, top)));
+                },
+                Err(_) => break
+            }
+        });
+    }
+
+    monitor::serving(&listener.local_addr()?.to_string(), config.workers);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => sender.send(stream).expect("ERROR: all workers stopped"),
+            Err(error) => eprintln!("ERROR: {error}")
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(stream: TcpStream, model: &Model, max_body_size: usize, top: usize) {
+    let timestamp = Instant::now();
+
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+
+    let (method, path, response) = match read_request(&stream, timestamp + TIMEOUT, max_body_size) {
+        Ok(request) => {
+            // A panic only fails its own request, the worker keeps serving. The model is never mutated,
+            // so it stays consistent after an unwind
+            let response = panic::catch_unwind(AssertUnwindSafe(|| respond(&request, model, top)))
+                .unwrap_or_else(|_| Err(HttpError::new(500, "internal error while handling the request")));
+
+            (request.method, request.path, response)
+        },
+        Err(error) => (String::from("-"), String::from("-"), Err(error))
+    };
+
+    let (status, body) = match response {
+        Ok(body) => (200, body),
+        Err(error) => (error.status, json!({ "error": error.message }))
+    };
+
+    // The client may already be gone, there is nobody left to report the error to
+    let _ = write_response(&stream, status, &body);
+
+    monitor::request(&method, &path, status, timestamp.elapsed());
+}
+
+fn read_request(mut stream: &TcpStream, deadline: Instant, max_body_size: usize) -> Result<Request, HttpError> {
+    let read_error = |error: io::Error| match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => HttpError::new(408, "request timed out"),
+        _ => HttpError::new(400, "malformed request")
+    };
+
+    let mut reader = BufReader::new(DeadlineReader { stream, deadline });
+    let mut lines = Vec::new();
+
+    {
+        let mut header = (&mut reader).take(MAX_HEADER_SIZE);
+
+        loop {
+            let mut line = String::new();
+            header.read_line(&mut line).map_err(read_error)?;
+
+            if !line.ends_with('\n') {
+                return Err(match header.limit() {
+                    0 => HttpError::new(431, format!("request header exceeds {MAX_HEADER_SIZE} bytes")),
+                    _ => HttpError::new(400, "request header ends unexpectedly")
+                });
+            }
+
+            match line.trim_end() {
+                "" => break,
+                line => lines.push(line.to_string())
+            }
+        }
+    }
+
+    let mut request_line = lines.first()
+        .map(|line| line.split_whitespace())
+        .ok_or_else(|| HttpError::new(400, "missing request line"))?;
+
+    let (method, target) = match (request_line.next(), request_line.next(), request_line.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => (method, target),
+        _ => return Err(HttpError::new(400, "malformed request line"))
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None)
+    };
+
+    let mut content_length = None;
+    let mut content_type = None;
+    let mut expect_continue = false;
+
+    for line in &lines[1..] {
+        let (name, value) = line.split_once(':').ok_or_else(|| HttpError::new(400, "malformed header field"))?;
+        let value = value.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = Some(
+                value.parse::<usize>().map_err(|_| HttpError::new(400, "invalid Content-Length"))?
+            ),
+            // Parameters like the charset are ignored
+            "content-type" => content_type = value.split(';').next().map(|media_type| media_type.trim().to_ascii_lowercase()),
+            "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+            _ => {}
+        }
+    }
+
+    let content_length = match (method, content_length) {
+        ("POST", None) => return Err(HttpError::new(411, "missing Content-Length")),
+        (_, content_length) => content_length.unwrap_or(0)
+    };
+
+    if content_length > max_body_size {
+        return Err(HttpError::new(413, format!("request body exceeds {max_body_size} bytes")));
+    }
+
+    // Clients wait for the confirmation before they send large bodies
+    if expect_continue {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").map_err(read_error)?;
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        content_type,
+        body
+    })
+}
+
+fn respond(request: &Request, model: &Model, top: usize) -> Result<Value, HttpError> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/health") => Ok(json!({
+            "status": "ok",
+            "composition": model.composition(),
+            "parameters": model.parameter_count()
+        })),
+        ("POST", "/predict") => predict(request, model, top),
+        (method, "/health" | "/predict") => Err(HttpError::new(405, format!("{method} is not allowed on {}", request.path))),
+        (_, path) => Err(HttpError::new(404, format!("{path} does not exist")))
+    }
+}
+
+fn predict(request: &Request, model: &Model, top: usize) -> Result<Value, HttpError> {
+    let top = match request.query.as_deref().and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("top="))) {
+        Some(top) => top.parse::<usize>().map_err(|_| HttpError::new(400, format!("'top' expects a number, got '{top}'")))?,
+        None => top
+    };
+
+    let inputs = match request.content_type.as_deref() {
+        Some("application/json") => {
+            let body = serde_json::from_slice::<PredictRequest>(&request.body)
+                .map_err(|error| HttpError::new(400, format!("invalid JSON body: {error}")))?;
+
+            match body.pixels {
+                Pixels::Single(pixels) => vec![pixels],
+                Pixels::Batch(inputs) => inputs
+            }
+        },
+        Some("image/x-portable-graymap" | "image/x-portable-pixmap" | "image/bmp") => {
+            let image = Image::decode(&request.body).map_err(|error| HttpError::new(400, error.to_string()))?;
+            vec![image.pixels]
+        },
+        _ => return Err(HttpError::new(415, "expected 'application/json' or a PGM, PPM or BMP image"))
+    };
+
+    let predictions = model.predict_batch(&inputs, top).map_err(|error| HttpError::new(422, error.to_string()))?;
+
+    Ok(json!({ "predictions": predictions.iter().map(prediction_json).collect::<Vec<_>>() }))
+}
+
+fn write_response(mut stream: &TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let body = body.to_string();
+
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    )?;
+
+    stream.flush()
+}